@@ -0,0 +1,10 @@
+// Runs the steg scan off the main thread. Loads its own copy of the module, which doesn't start
+// the app when there's no window
+import init, { scan } from "./pkg/graphics_toolbox.js";
+
+const ready = init();
+
+onmessage = async ({ data }) => {
+  await ready;
+  postMessage(scan(data.width, data.height, data.depth, data.values));
+};
@@ -13,6 +13,12 @@ struct Data {
     @location(1) win_dim: vec2<f32>,
     @location(2) pos: vec2<f32>,
     @location(3) scale: f32,
+    // added to the mip level picked by textureSampleBias, negative is sharper
+    @location(4) lod_bias: f32,
+    // -1.0 where the view is mirrored along that image axis
+    @location(5) flip: vec2<f32>,
+    // clockwise, in radians
+    @location(6) rotation: f32,
 }
 
 struct VertexOutput {
@@ -25,18 +31,25 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
     let pos = vec2<f32>(f32((in_vertex_index & 2u) >> 1), f32(in_vertex_index & 1u));
     var out: VertexOutput;
     out.tex_coords = vec2<f32>(pos.x, 1 - pos.y);
-    var scale: vec2<f32>;
-    let aspect = data.win_dim.x / data.win_dim.y * data.img_dim.y / data.img_dim.x;
+    out.pos = vec4<f32>(view_transform(pos), 0.0, 1.0);
+    return out;
+}
+
+// image quad corner (0.0-1.0, y up) to clip space: pan, flip, rotate, then zoom
+fn view_transform(corner: vec2<f32>) -> vec2<f32> {
+    let p = ((corner + data.pos) * 2.0 - 1) * data.img_dim / 2 * data.flip;
+    let c = cos(data.rotation);
+    let s = sin(data.rotation);
+    let rotated = vec2<f32>(c * p.x + s * p.y, c * p.y - s * p.x);
+    return rotated * data.scale * view_fit() * 2 / data.win_dim;
+}
+
+// window pixels per image pixel at scale 1
+fn view_fit() -> f32 {
     if (data.win_dim.y > data.win_dim.x) {
-        scale = vec2<f32>(data.scale, data.scale * aspect);
-    } else {
-        scale = vec2<f32>(data.scale / aspect, data.scale);
+        return data.win_dim.x / data.img_dim.x;
     }
-    out.pos = vec4<f32>(
-        ((pos + data.pos) * 2.0 - 1) * scale,
-        0.0, 1.0
-    );
-    return out;
+    return data.win_dim.y / data.img_dim.y;
 }
 
 @group(0) @binding(0)
@@ -47,15 +60,22 @@ var sampler1: sampler;
 var sampler2: sampler;
 @group(0) @binding(3)
 var<uniform> data: Data;
+// what the frequency filter (D) last produced, a transparent pixel before that
+@group(0) @binding(4)
+var result: texture_2d<f32>;
+// the image with its tiles put back in order (backslash), a transparent pixel before that
+@group(0) @binding(5)
+var tiles: texture_2d<f32>;
 
 fn sampleClamp(texture: texture_2d<f32>, sampler1: sampler, v: vec2<f32>) -> vec4<f32> {
     if (v.x < 0.0 || v.x > 1.0 || v.y < 0.0 || v.y > 1.0) {
         return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     }
-    return textureSample(texture, sampler1, v);
+    return textureSampleBias(texture, sampler1, v, data.lod_bias);
 }
 
 @fragment
 fn fs_main(inp: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(sampleClamp(texture, sampler1, transform_coords2(inp.tex_coords)).xyz / 2, 1.0);
+    let color = sampleClamp(texture, sampler1, transform_coords2(inp.tex_coords));
+    return vec4<f32>(color.xyz / 2, color.a);
 }
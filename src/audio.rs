@@ -0,0 +1,335 @@
+//! Audio tracks turned into spectrogram images, for pictures hidden in the frequencies. The
+//! spectrogram is loaded as the image, so everything else works on it as it would on a picture:
+//! time runs left to right and frequency bottom to top
+use std::{fmt::Display, io::Cursor, str::FromStr};
+
+use image::{DynamicImage, ImageBuffer, Luma};
+use rustfft::{num_complex::Complex32, FftPlanner};
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error, formats::FormatOptions,
+    io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+/// Watched like "nuero.png", whichever changed last is shown
+pub const AUDIO_FILES: [&str; 3] = ["nuero.wav", "nuero.flac", "nuero.ogg"];
+/// Edited by hand like `lsb::SPEC_FILE`, the spectrogram is redone whenever it changes
+pub const SETTINGS_FILE: &str = "spectrogram.txt";
+
+/// Decoded track, with the channels mixed down to one
+#[derive(Debug, Clone)]
+pub struct Audio {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+/// Decode a WAV, FLAC or Ogg Vorbis file. `name` is only used to guess the format
+pub fn decode(name: &str, contents: Vec<u8>) -> Result<Audio, Error> {
+    let mut hint = Hint::new();
+    if let Some((_, extension)) = name.rsplit_once('.') {
+        hint.with_extension(extension);
+    }
+    let source = MediaSourceStream::new(Box::new(Cursor::new(contents)), Default::default());
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or(Error::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // a damaged packet only leaves a gap
+            Err(Error::DecodeError(err)) => {
+                log::warn!("{err}");
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let spec = *decoded.spec();
+        sample_rate = spec.rate;
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        let channels = spec.channels.count().max(1);
+        samples.extend(
+            buffer
+                .samples()
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+    }
+    Ok(Audio {
+        sample_rate,
+        samples,
+    })
+}
+
+/// Shape each frame is multiplied by before its FFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    pub const ALL: [Self; 4] = [Self::Rectangular, Self::Hann, Self::Hamming, Self::Blackman];
+    pub fn cycle(self) -> Self {
+        let i = Self::ALL.iter().position(|&window| window == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rectangular => "rectangular",
+            Self::Hann => "hann",
+            Self::Hamming => "hamming",
+            Self::Blackman => "blackman",
+        }
+    }
+    fn weights(self, len: usize) -> Vec<f32> {
+        let tau = std::f32::consts::TAU;
+        (0..len)
+            .map(|i| {
+                let x = i as f32 / len as f32;
+                match self {
+                    Self::Rectangular => 1.0,
+                    Self::Hann => 0.5 - 0.5 * (tau * x).cos(),
+                    Self::Hamming => 0.54 - 0.46 * (tau * x).cos(),
+                    Self::Blackman => 0.42 - 0.5 * (tau * x).cos() + 0.08 * (2.0 * tau * x).cos(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// What `SETTINGS_FILE` says, one item per line: `window N` samples per frame, `hop N` samples
+/// between frames, `function hann|hamming|blackman|rectangular`, `scale linear|log` for the
+/// frequency axis and `range DB` below the loudest bin that still shows. Lines starting with `#`
+/// are comments and anything left out keeps its default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub window: usize,
+    pub hop: usize,
+    pub function: Window,
+    pub log: bool,
+    pub range: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window: 2048,
+            hop: 512,
+            function: Window::Hann,
+            log: false,
+            range: 90.0,
+        }
+    }
+}
+
+impl Display for Settings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "window {}", self.window)?;
+        writeln!(f, "hop {}", self.hop)?;
+        writeln!(f, "function {}", self.function.name())?;
+        writeln!(f, "scale {}", if self.log { "log" } else { "linear" })?;
+        write!(f, "range {}", self.range)
+    }
+}
+
+impl FromStr for Settings {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::default();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(char::is_whitespace)
+                .map(|(key, value)| (key, value.trim()))
+                .ok_or_else(|| format!("can't understand {line:?}"))?;
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("{value} isn't a valid size in {line:?}"))
+            };
+            match key {
+                "window" => ret.window = number()?.max(2),
+                "hop" => ret.hop = number()?,
+                "function" => {
+                    ret.function = Window::ALL
+                        .into_iter()
+                        .find(|window| window.name() == value)
+                        .ok_or_else(|| format!("no window function called {value}"))?;
+                }
+                "scale" => {
+                    ret.log = match value {
+                        "log" => true,
+                        "linear" => false,
+                        _ => return Err(format!("scale is log or linear, not {value}")),
+                    }
+                }
+                "range" => {
+                    ret.range = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|&range| range > 0.0)
+                        .ok_or_else(|| format!("{value} isn't a valid range in {line:?}"))?;
+                }
+                _ => return Err(format!("can't understand {line:?}")),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Magnitude of every frame in dB, scaled so `range` below the loudest bin is black and the
+/// loudest is white. Frames get further apart than `hop` if there would be more than
+/// `max_width` of them; the hop used comes back with the image
+pub fn spectrogram(audio: &Audio, settings: &Settings, max_width: u32) -> (DynamicImage, usize) {
+    let window = settings.window;
+    let bins = window / 2 + 1;
+    let frames_for = |hop: usize| audio.samples.len().saturating_sub(window) / hop + 1;
+    let mut hop = settings.hop;
+    if frames_for(hop) > max_width as usize {
+        hop = audio.samples.len().div_ceil(max_width as usize).max(1);
+    }
+    let frames = frames_for(hop);
+    let weights = settings.function.weights(window);
+    let fft = FftPlanner::new().plan_fft_forward(window);
+    let mut db = Vec::with_capacity(frames * bins);
+    let mut buffer = vec![Complex32::default(); window];
+    for frame in 0..frames {
+        let start = frame * hop;
+        for (i, (z, weight)) in buffer.iter_mut().zip(&weights).enumerate() {
+            // the last frame is padded with silence
+            let sample = audio.samples.get(start + i).copied().unwrap_or_default();
+            *z = Complex32::new(sample * weight, 0.0);
+        }
+        fft.process(&mut buffer);
+        db.extend(
+            buffer[..bins]
+                .iter()
+                .map(|z| 20.0 * (z.norm() + 1e-12).log10()),
+        );
+    }
+    let loudest = db.iter().copied().fold(f32::MIN, f32::max);
+    // bin at row y, counting from the top, fractional on a log scale
+    let height = bins as u32;
+    let bin_at = |y: u32| {
+        let t = 1.0 - y as f32 / (height - 1).max(1) as f32;
+        if settings.log {
+            // from the first bin above DC up to Nyquist
+            ((bins - 1) as f32).powf(t)
+        } else {
+            t * (bins - 1) as f32
+        }
+    };
+    let rows: Vec<f32> = (0..height).map(bin_at).collect();
+    let img = ImageBuffer::from_fn(frames as u32, height, |x, y| {
+        let frame = &db[x as usize * bins..][..bins];
+        let bin = rows[y as usize];
+        let (i, fraction) = (bin.floor() as usize, bin.fract());
+        let value = frame[i] * (1.0 - fraction) + frame[(i + 1).min(bins - 1)] * fraction;
+        let level = ((value - loudest + settings.range) / settings.range).clamp(0.0, 1.0);
+        Luma([(level * u16::MAX as f32).round() as u16])
+    });
+    (DynamicImage::ImageLuma16(img), hop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = Settings {
+            window: 1024,
+            hop: 128,
+            function: Window::Blackman,
+            log: true,
+            range: 60.5,
+        };
+        assert_eq!(settings.to_string().parse::<Settings>(), Ok(settings));
+        let text = "# louder\n  range 40\n\nscale log\n";
+        let settings: Settings = text.parse().unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                log: true,
+                range: 40.0,
+                ..Default::default()
+            }
+        );
+        assert_eq!("window 1".parse::<Settings>().unwrap().window, 2);
+        for bad in [
+            "window 0",
+            "hop -3",
+            "function kaiser",
+            "scale mel",
+            "range 0",
+            "window",
+            "colour red",
+        ] {
+            assert!(bad.parse::<Settings>().is_err(), "{bad}");
+        }
+    }
+
+    /// Row that's brightest in the middle column
+    fn loudest_row(img: &DynamicImage) -> u32 {
+        let img = img.to_luma16();
+        let x = img.width() / 2;
+        (0..img.height())
+            .max_by_key(|&y| img.get_pixel(x, y)[0])
+            .unwrap()
+    }
+
+    #[test]
+    fn tone_lands_on_its_row() {
+        // 1 kHz at 8 kHz is bin 32 of 129, a quarter of the way up
+        let audio = Audio {
+            sample_rate: 8000,
+            samples: (0..8000)
+                .map(|i| (std::f32::consts::TAU * 1000.0 * i as f32 / 8000.0).sin())
+                .collect(),
+        };
+        let mut settings = Settings {
+            window: 256,
+            hop: 256,
+            ..Default::default()
+        };
+        let (img, hop) = spectrogram(&audio, &settings, 4096);
+        assert_eq!(hop, 256);
+        assert_eq!((img.width(), img.height()), (31, 129));
+        assert_eq!(loudest_row(&img), 96);
+        // 128^(5/7) is 32, so 2/7 of the way down
+        settings.log = true;
+        let (img, _) = spectrogram(&audio, &settings, 4096);
+        assert!((36..=37).contains(&loudest_row(&img)));
+        // too many frames for the width makes the hop longer
+        let (img, hop) = spectrogram(&audio, &settings, 10);
+        assert_eq!(hop, 800);
+        assert!(img.width() <= 10);
+    }
+}
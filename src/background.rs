@@ -0,0 +1,101 @@
+//! Everything drawn under the user shader: the clear color, a checkerboard behind the image so
+//! transparent pixels are visible, and an outline marking where the image ends
+use std::borrow::Cow;
+
+use wgpu::{BindGroupLayout, Device, RenderPipeline};
+
+/// Clear colors cycled through with `B`
+const BACKGROUNDS: [(&str, wgpu::Color); 5] = [
+    ("green", wgpu::Color::GREEN),
+    ("black", wgpu::Color::BLACK),
+    (
+        "gray",
+        wgpu::Color {
+            r: 0.2,
+            g: 0.2,
+            b: 0.2,
+            a: 1.0,
+        },
+    ),
+    ("white", wgpu::Color::WHITE),
+    (
+        "magenta",
+        wgpu::Color {
+            r: 1.0,
+            g: 0.0,
+            b: 1.0,
+            a: 1.0,
+        },
+    ),
+];
+
+#[derive(Debug)]
+pub struct Background {
+    checkerboard_pipeline: RenderPipeline,
+    outline_pipeline: RenderPipeline,
+    color: usize,
+    pub checkerboard: bool,
+    pub outline: bool,
+}
+
+impl Background {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("background"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/background.wgsl"),
+            ))),
+        });
+        Self {
+            checkerboard_pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_main",
+                "fs_checkerboard",
+                format,
+                Some(wgpu::BlendState::ALPHA_BLENDING),
+            ),
+            outline_pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_outline",
+                "fs_outline",
+                format,
+                Some(wgpu::BlendState::ALPHA_BLENDING),
+            ),
+            color: 0,
+            checkerboard: true,
+            outline: true,
+        }
+    }
+    pub fn clear_color(&self) -> wgpu::Color {
+        BACKGROUNDS[self.color].1
+    }
+    pub fn color_name(&self) -> &'static str {
+        BACKGROUNDS[self.color].0
+    }
+    /// Switch to the clear color called `name`, if there is one
+    pub fn set_color(&mut self, name: &str) {
+        if let Some(i) = BACKGROUNDS.iter().position(|(color, _)| *color == name) {
+            self.color = i;
+        }
+    }
+    pub fn next_color(&mut self) -> &'static str {
+        self.color = (self.color + 1) % BACKGROUNDS.len();
+        BACKGROUNDS[self.color].0
+    }
+    /// Expects the bind group at index 0 to already be set
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        if self.outline {
+            rpass.set_pipeline(&self.outline_pipeline);
+            rpass.draw(0..4, 0..1);
+        }
+        if self.checkerboard {
+            rpass.set_pipeline(&self.checkerboard_pipeline);
+            rpass.draw(0..4, 0..1);
+        }
+    }
+}
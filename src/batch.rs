@@ -0,0 +1,235 @@
+//! One shader over a whole folder of images:
+//!
+//! ```text
+//! graphics-toolbox batch --input 'frames/*.png' --shader reveal.wgsl -o 'out/{stem}.png'
+//! ```
+//!
+//! Images are decoded and encoded on `--jobs` threads each, while the GPU renders them one at a
+//! time in between. A file that fails doesn't stop the rest, all failures are listed at the end
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    export,
+    headless::{value, Headless},
+};
+
+#[derive(Debug, Default)]
+pub struct BatchArgs {
+    input: String,
+    shader: String,
+    output: String,
+    jobs: Option<usize>,
+    fallback: bool,
+}
+
+impl BatchArgs {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut ret = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--input" | "-i" => ret.input = value(&mut args, &arg)?,
+                "--shader" | "-s" => ret.shader = value(&mut args, &arg)?,
+                "--output" | "-o" => ret.output = value(&mut args, &arg)?,
+                "--jobs" | "-j" => {
+                    let jobs = value(&mut args, &arg)?;
+                    ret.jobs = Some(
+                        jobs.parse()
+                            .ok()
+                            .filter(|&jobs| jobs > 0)
+                            .ok_or_else(|| format!("bad job count {jobs}"))?,
+                    );
+                }
+                "--fallback" => ret.fallback = true,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if ret.input.is_empty() || ret.shader.is_empty() || ret.output.is_empty() {
+            return Err("--input, --shader and -o are required".to_owned());
+        }
+        Ok(ret)
+    }
+}
+
+/// Fill in `{stem}` (file name without extension), `{name}` (file name) and `{index}` (position
+/// among the sorted inputs, zero-padded to 4 digits). A template without any of those is taken
+/// as a folder to put `{stem}.png` in
+fn output_path(template: &str, input: &Path, index: usize) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let name = input.file_name().unwrap_or_default().to_string_lossy();
+    if !template.contains('{') {
+        return Path::new(template).join(format!("{stem}.png"));
+    }
+    template
+        .replace("{stem}", &stem)
+        .replace("{name}", &name)
+        .replace("{index}", &format!("{index:04}"))
+        .into()
+}
+
+/// Indices of the outputs to render, and of the ones that would overwrite an earlier output
+fn split_duplicates(outputs: &[PathBuf]) -> (Vec<usize>, Vec<usize>) {
+    let mut seen = HashSet::new();
+    (0..outputs.len()).partition(|&i| seen.insert(&outputs[i]))
+}
+
+pub fn write_output(
+    path: &Path,
+    format: wgpu::TextureFormat,
+    size: (u32, u32),
+    pixels: Vec<u8>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    for (name, contents) in export::encode(&path.to_string_lossy(), format, size, pixels)? {
+        std::fs::write(name, contents)?;
+    }
+    Ok(())
+}
+
+pub fn run(args: BatchArgs) -> Result<(), Box<dyn Error>> {
+    let mut failures = Vec::new();
+    let mut inputs = Vec::new();
+    for entry in glob::glob(&args.input)? {
+        match entry {
+            Ok(path) if path.is_file() => inputs.push(path),
+            Ok(_) => {}
+            Err(err) => failures.push((err.path().to_owned(), err.to_string())),
+        }
+    }
+    let total = inputs.len() + failures.len();
+    if total == 0 {
+        return Err(format!("nothing matches {}", args.input).into());
+    }
+    let outputs: Vec<_> = (inputs.iter().enumerate())
+        .map(|(i, input)| output_path(&args.output, input, i))
+        .collect();
+    let (todo, duplicates) = split_duplicates(&outputs);
+    for i in duplicates {
+        let err = format!(
+            "{} is also the output of another input",
+            outputs[i].display()
+        );
+        failures.push((inputs[i].clone(), err));
+    }
+
+    let code = std::fs::read_to_string(&args.shader)?;
+    let headless = pollster::block_on(Headless::new(args.fallback))?;
+    // every output has to be rendered to the same format, since there's only one pipeline
+    let format = headless.target_format(&args.output);
+    if let Some(output) = outputs
+        .iter()
+        .find(|output| headless.target_format(&output.to_string_lossy()) != format)
+    {
+        return Err(format!("{} would have a different format", output.display()).into());
+    }
+    let pipeline =
+        pollster::block_on(headless.pipeline(&args.shader, &code, format, &HashMap::new()))?;
+
+    let jobs = args
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |jobs| jobs.get()));
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(failures);
+    let fail = |i: usize, err: String| failures.lock().unwrap().push((inputs[i].clone(), err));
+    let (decoded_tx, decoded_rx) = mpsc::sync_channel(jobs);
+    let (rendered_tx, rendered_rx) = mpsc::sync_channel::<(usize, (u32, u32), Vec<u8>)>(jobs);
+    let rendered_rx = Mutex::new(rendered_rx);
+    thread::scope(|s| {
+        for _ in 0..jobs {
+            let decoded_tx = decoded_tx.clone();
+            s.spawn(|| {
+                let decoded_tx = decoded_tx;
+                while let Some(&i) = todo.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if decoded_tx.send((i, image::open(&inputs[i]))).is_err() {
+                        break;
+                    }
+                }
+            });
+            s.spawn(|| loop {
+                let Ok((i, size, pixels)) = rendered_rx.lock().unwrap().recv() else {
+                    break;
+                };
+                match write_output(&outputs[i], format, size, pixels) {
+                    Ok(()) => log::info!("{} -> {}", inputs[i].display(), outputs[i].display()),
+                    Err(err) => fail(i, err.to_string()),
+                }
+            });
+        }
+        drop(decoded_tx);
+        // the device lives on this thread, so images go through the GPU in whatever order they
+        // finish decoding
+        for (i, img) in decoded_rx {
+            let img = match img {
+                Ok(img) => img,
+                Err(err) => {
+                    fail(i, err.to_string());
+                    continue;
+                }
+            };
+            let size = (img.width(), img.height());
+            match pollster::block_on(headless.render(&pipeline, img, format)) {
+                Ok(pixels) => rendered_tx.send((i, size, pixels)).unwrap(),
+                Err(err) => fail(i, err.to_string()),
+            }
+        }
+        drop(rendered_tx);
+    });
+
+    let mut failures = failures.into_inner().unwrap();
+    println!("{} of {total} images rendered", total - failures.len());
+    if failures.is_empty() {
+        return Ok(());
+    }
+    failures.sort();
+    for (path, err) in &failures {
+        eprintln!("{}: {err}", path.display());
+    }
+    Err(format!("{} images failed", failures.len()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_template_expands() {
+        let input = Path::new("frames/frame.01.png");
+        assert_eq!(
+            output_path("out/{stem}.png", input, 3),
+            Path::new("out/frame.01.png")
+        );
+        assert_eq!(
+            output_path("out/{name}.bmp", input, 3),
+            Path::new("out/frame.01.png.bmp")
+        );
+        assert_eq!(
+            output_path("out/{index}-{stem}.png", input, 12),
+            Path::new("out/0012-frame.01.png")
+        );
+        // no placeholders means a folder
+        assert_eq!(output_path("out", input, 3), Path::new("out/frame.01.png"));
+    }
+
+    #[test]
+    fn duplicate_outputs_are_skipped() {
+        let inputs = ["a/x.png", "b/x.png", "a/y.png", "c/x.png"].map(Path::new);
+        let outputs: Vec<_> = (inputs.iter().enumerate())
+            .map(|(i, input)| output_path("out/{stem}.png", input, i))
+            .collect();
+        assert_eq!(split_duplicates(&outputs), (vec![0, 2], vec![1, 3]));
+        let outputs: Vec<_> = (inputs.iter().enumerate())
+            .map(|(i, input)| output_path("out/{index}.png", input, i))
+            .collect();
+        assert_eq!(split_duplicates(&outputs), (vec![0, 1, 2, 3], vec![]));
+    }
+}
@@ -0,0 +1,236 @@
+//! Built-in bit-plane viewer: one bit of one channel in black and white, independent of the user
+//! shader. Reads a separate integer copy of the image, so what's shown is exactly the file's bits
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroupLayout, Device, Queue, RenderPipeline};
+
+use crate::raw::RawImage;
+
+/// 8-bit images are widened into this too, so there's only one kind of raw texture
+pub const RAW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Uint;
+/// Size of the `Plane` uniform in src/shaders/bitplane.wgsl
+pub const PLANE_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Luma,
+}
+
+impl Channel {
+    pub const ALL: [Self; 5] = [Self::Red, Self::Green, Self::Blue, Self::Alpha, Self::Luma];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Red => "R",
+            Self::Green => "G",
+            Self::Blue => "B",
+            Self::Alpha => "A",
+            Self::Luma => "luma",
+        }
+    }
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|&c| c == self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plane {
+    pub channel: Channel,
+    pub bit: u32,
+    /// Show every bit of R, G, B and A at once, a row per channel and the top bit on the left
+    pub grid: bool,
+    /// Bits per channel of the image, 8 or 16
+    pub depth: u32,
+}
+
+impl Plane {
+    /// The least significant bit of red, where things usually hide
+    pub fn new(depth: u32) -> Self {
+        Self {
+            channel: Channel::Red,
+            bit: 0,
+            grid: false,
+            depth,
+        }
+    }
+    /// Step through bits, moving on to the next or previous channel past the ends
+    pub fn step_bit(&mut self, step: i32) {
+        let planes = (self.depth * Channel::ALL.len() as u32) as i32;
+        let i = (self.channel.index() as u32 * self.depth + self.bit) as i32;
+        let i = (i + step).rem_euclid(planes) as u32;
+        self.channel = Channel::ALL[(i / self.depth) as usize];
+        self.bit = i % self.depth;
+    }
+    pub fn step_channel(&mut self, step: i32) {
+        let count = Channel::ALL.len() as i32;
+        self.channel =
+            Channel::ALL[(self.channel.index() as i32 + step).rem_euclid(count) as usize];
+    }
+    /// After loading an image that may have a different bit depth
+    pub fn set_depth(&mut self, depth: u32) {
+        self.depth = depth;
+        self.bit = self.bit.min(depth - 1);
+    }
+    pub fn label(&self) -> String {
+        match self.grid {
+            true => "bit planes".to_owned(),
+            false => format!("bit plane {}{}", self.channel.name(), self.bit),
+        }
+    }
+    /// Size of what's drawn, which for the grid is all the planes side by side
+    pub fn shown_dim(&self, img_dim: (f32, f32)) -> (f32, f32) {
+        match self.grid {
+            true => (img_dim.0 * self.depth as f32, img_dim.1 * 4.0),
+            false => img_dim,
+        }
+    }
+    /// Which plane and image pixel a pixel of [`Plane::shown_dim`] is
+    pub fn locate(&self, (x, y): (f32, f32), img_dim: (f32, f32)) -> (Self, (f32, f32)) {
+        if !self.grid {
+            return (*self, (x, y));
+        }
+        let column = (x / img_dim.0).floor().clamp(0.0, self.depth as f32 - 1.0) as u32;
+        let row = (y / img_dim.1).floor().clamp(0.0, 3.0) as usize;
+        let plane = Self {
+            channel: Channel::ALL[row],
+            bit: self.depth - 1 - column,
+            grid: false,
+            depth: self.depth,
+        };
+        (plane, (x.rem_euclid(img_dim.0), y.rem_euclid(img_dim.1)))
+    }
+    pub fn to_bytes(self) -> [u8; PLANE_SIZE] {
+        let mut ret = [0u8; PLANE_SIZE];
+        for (dst, src) in ret.chunks_exact_mut(4).zip([
+            self.channel.index() as u32,
+            self.bit,
+            self.grid as u32,
+            self.depth,
+        ]) {
+            dst.copy_from_slice(&src.to_le_bytes());
+        }
+        ret
+    }
+}
+
+/// Upload the image's channel values as integers
+pub fn raw_texture(device: &Device, queue: &Queue, raw: &RawImage) -> wgpu::Texture {
+    let bytes: Vec<u8> = raw.values.iter().flat_map(|x| x.to_ne_bytes()).collect();
+    let size = wgpu::Extent3d {
+        width: raw.width,
+        height: raw.height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("raw image"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: RAW_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        &bytes,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(8 * raw.width),
+            rows_per_image: Some(raw.height),
+        },
+        size,
+    );
+    texture
+}
+
+/// Group 1 of the bit-plane pipeline: the raw texture and a viewport's `Plane`
+pub fn bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("bit plane"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Uint,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+#[derive(Debug)]
+pub struct BitPlanes {
+    pipeline: RenderPipeline,
+}
+
+impl BitPlanes {
+    pub fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        plane_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("bit plane"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/bitplane.wgsl"),
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[layout, plane_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("bit plane"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_bitplane",
+                compilation_options: Default::default(),
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        Self { pipeline }
+    }
+    /// Draw in place of the user shader. Expects the bind group at index 0 to already be set
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass, plane_group: &wgpu::BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(1, plane_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+}
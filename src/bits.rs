@@ -0,0 +1,223 @@
+//! Packing bits that a shader picked out back into bytes.
+//!
+//! The shader is rendered into a linear 8-bit target at the image's resolution, and each output
+//! channel says what that pixel contributes: 0.0 for nothing, 0.5 for a 0 bit and 1.0 for a 1
+//! bit. So `return vec4(f32(lsb) * 0.5 + 0.5, 0.0, 0.0, 0.0);` emits one bit per pixel from red.
+//!
+//! How the bits are packed is written as a comma separated list, `rgba,msb,xy` for every channel
+//! of every pixel row by row, filling each byte from the top. `word=<n>` and `le` reverse the
+//! bytes of every n-byte word.
+use std::{fmt, str::FromStr};
+
+/// Edited by hand, used by every ctrl+B after it changes
+pub const PACKING_FILE: &str = "bits.txt";
+
+/// Exact 0, 128 and 255, without the sRGB curve getting in the way
+pub const BITS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Channel indices for names like "rgba" or "bgr"
+pub fn parse_channels(names: &str) -> Result<Vec<usize>, String> {
+    names
+        .chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            'r' => Ok(0),
+            'g' => Ok(1),
+            'b' => Ok(2),
+            'a' => Ok(3),
+            _ => Err(format!(
+                "unknown channel {c} in {names}, expected r, g, b or a"
+            )),
+        })
+        .collect()
+}
+
+/// Names for channel indices, the other way around from [`parse_channels`]
+pub fn channel_names(channels: &[usize]) -> String {
+    channels.iter().map(|&c| b"rgba"[c] as char).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packing {
+    /// Channel indices to read in each pixel, in the order their bits come out
+    pub channels: Vec<usize>,
+    /// First bit goes in the top of each byte
+    pub msb_first: bool,
+    /// Walk down each column before moving right, instead of along each row
+    pub column_major: bool,
+    /// Bytes per word for `little_endian`
+    pub word: usize,
+    /// Reverse the bytes in every word
+    pub little_endian: bool,
+}
+
+impl Default for Packing {
+    fn default() -> Self {
+        Self {
+            channels: vec![0, 1, 2, 3],
+            msb_first: true,
+            column_major: false,
+            word: 1,
+            little_endian: false,
+        }
+    }
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = channel_names(&self.channels);
+        let order = if self.msb_first { "msb" } else { "lsb" };
+        let scan = if self.column_major { "yx" } else { "xy" };
+        write!(f, "{channels},{order},{scan}")?;
+        if self.word != 1 {
+            write!(f, ",word={}", self.word)?;
+        }
+        if self.little_endian {
+            write!(f, ",le")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Packing {
+    type Err = String;
+    /// Anything left out keeps its default
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::default();
+        for part in s.trim().split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            match part {
+                "msb" => ret.msb_first = true,
+                "lsb" => ret.msb_first = false,
+                "xy" => ret.column_major = false,
+                "yx" => ret.column_major = true,
+                "be" => ret.little_endian = false,
+                "le" => ret.little_endian = true,
+                _ if part.starts_with("word=") => {
+                    ret.word = part[5..]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&word| word > 0)
+                        .ok_or_else(|| format!("{part} isn't a valid word size"))?;
+                }
+                _ => ret.channels = parse_channels(part)?,
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Bits marked in RGBA8 pixels, packed into bytes. Returns the bytes and how many bits there
+/// were, the last byte is padded with zeros
+pub fn extract(pixels: &[u8], (width, height): (u32, u32), packing: &Packing) -> (Vec<u8>, usize) {
+    let (width, height) = (width as usize, height as usize);
+    let order: Box<dyn Iterator<Item = (usize, usize)>> = match packing.column_major {
+        false => Box::new((0..height).flat_map(|y| (0..width).map(move |x| (x, y)))),
+        true => Box::new((0..width).flat_map(|x| (0..height).map(move |y| (x, y)))),
+    };
+    let mut bytes = Vec::new();
+    let mut count = 0;
+    for (x, y) in order {
+        let pixel = &pixels[(y * width + x) * 4..][..4];
+        for &channel in &packing.channels {
+            // anything in the bottom quarter is a gap, the middle half a 0, the rest a 1
+            let bit = match pixel[channel] {
+                0..=63 => continue,
+                64..=191 => 0,
+                _ => 1,
+            };
+            if count % 8 == 0 {
+                bytes.push(0);
+            }
+            let shift = match packing.msb_first {
+                true => 7 - count % 8,
+                false => count % 8,
+            };
+            *bytes.last_mut().unwrap() |= bit << shift;
+            count += 1;
+        }
+    }
+    if packing.little_endian && packing.word > 1 {
+        for word in bytes.chunks_mut(packing.word) {
+            word.reverse();
+        }
+    }
+    (bytes, count)
+}
+
+/// Like `hexdump -C`, stopping after `limit` bytes
+pub fn hexdump(bytes: &[u8], limit: usize) -> String {
+    let mut ret = String::new();
+    for (i, line) in bytes[..bytes.len().min(limit)].chunks(16).enumerate() {
+        let hex: Vec<_> = line.iter().map(|byte| format!("{byte:02x}")).collect();
+        let ascii: String = line
+            .iter()
+            .map(|&byte| match byte {
+                0x20..=0x7e => byte as char,
+                _ => '.',
+            })
+            .collect();
+        ret += &format!("{:08x}  {:<47}  |{ascii}|\n", i * 16, hex.join(" "));
+    }
+    if bytes.len() > limit {
+        ret += &format!("... {} more bytes\n", bytes.len() - limit);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packing_round_trips() {
+        assert_eq!(parse_channels("BgR"), Ok(vec![2, 1, 0]));
+        assert!(parse_channels("rgx").is_err());
+        for text in [
+            "rgba,msb,xy",
+            "bgr,lsb,yx",
+            "r,msb,xy,word=4,le",
+            "ga,lsb,xy,word=2",
+        ] {
+            let packing: Packing = text.parse().unwrap();
+            assert_eq!(packing.to_string(), text);
+        }
+        let packing: Packing = " b , yx ".parse().unwrap();
+        assert_eq!(packing.channels, [2]);
+        assert!(packing.column_major && packing.msb_first);
+        assert_eq!("".parse::<Packing>().unwrap(), Packing::default());
+        assert!("rgbx".parse::<Packing>().is_err());
+        assert!("word=0".parse::<Packing>().is_err());
+    }
+
+    #[test]
+    fn extract_packs_marked_bits() {
+        // 2x2, 255 is a 1, 128 a 0 and 0 nothing
+        #[rustfmt::skip]
+        let pixels = [
+            255, 128, 0, 255,    128, 128, 128, 255,
+            255, 0, 0, 0,        128, 255, 255, 128,
+        ];
+        let pack = |text: &str| extract(&pixels, (2, 2), &text.parse().unwrap());
+        assert_eq!(pack("rgba,msb,xy"), (vec![0b1010_0011, 0b0110_0000], 12));
+        assert_eq!(pack("rgba,lsb,xy"), (vec![0b1100_0101, 0b0000_0110], 12));
+        assert_eq!(pack("rgba,msb,yx"), (vec![0b1011_0001, 0b0110_0000], 12));
+        assert_eq!(pack("r"), (vec![0b1010_0000], 4));
+    }
+
+    #[test]
+    fn extract_reverses_words() {
+        let bytes = [0x01, 0x02, 0x03, 0x04];
+        // one bit per pixel in red, 8x4
+        let pixels: Vec<u8> = bytes
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1))
+            .flat_map(|bit| [if bit == 1 { 255 } else { 128 }, 0, 0, 0])
+            .collect();
+        let pack = |text: &str| extract(&pixels, (8, 4), &text.parse().unwrap()).0;
+        assert_eq!(pack("r"), bytes);
+        assert_eq!(pack("r,word=2,le"), [0x02, 0x01, 0x04, 0x03]);
+        assert_eq!(pack("r,word=4,le"), [0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(pack("r,word=4,be"), bytes);
+        // a short word at the end is reversed on its own
+        assert_eq!(pack("r,word=3,le"), [0x03, 0x02, 0x01, 0x04]);
+    }
+}
@@ -0,0 +1,226 @@
+//! Counting the distinct colors of the image, and isolating one of them. Flat-color images often
+//! hide text in a color one step off from its surroundings, which this makes obvious
+use std::{borrow::Cow, collections::HashMap};
+
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroupLayout, Device, Queue, RenderPipeline};
+
+use crate::raw::RawImage;
+
+/// Size of the `Isolate` uniform in src/shaders/isolate.wgsl
+pub const ISOLATE_SIZE: usize = 32;
+
+/// RGBA in the image's own units
+pub type Color = [u16; 4];
+
+/// Every color in the image with how many pixels have it, most common first
+pub fn unique_colors(raw: &RawImage) -> Vec<(Color, u32)> {
+    let mut counts = HashMap::<Color, u32>::new();
+    for pixel in raw.values.chunks_exact(4) {
+        *counts.entry(pixel.try_into().unwrap()).or_default() += 1;
+    }
+    let mut ret: Vec<_> = counts.into_iter().collect();
+    ret.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    ret
+}
+
+/// Like `#ff8000ff`, or with 4 digits per channel for 16-bit images
+pub fn hex(color: Color, depth: u32) -> String {
+    let digits = depth as usize / 4;
+    let channels: String = color
+        .iter()
+        .map(|value| format!("{value:0digits$x}"))
+        .collect();
+    format!("#{channels}")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Isolate {
+    pub color: Color,
+    /// Largest difference per channel that still counts as the same color
+    pub tolerance: u16,
+}
+
+impl Isolate {
+    pub fn matches(&self, pixel: &[u16]) -> bool {
+        pixel
+            .iter()
+            .zip(self.color)
+            .all(|(&a, b)| a.abs_diff(b) <= self.tolerance)
+    }
+    /// Pixels of the image that show through
+    pub fn count(&self, raw: &RawImage) -> usize {
+        raw.values
+            .chunks_exact(4)
+            .filter(|pixel| self.matches(pixel))
+            .count()
+    }
+    pub fn to_bytes(self) -> [u8; ISOLATE_SIZE] {
+        let mut ret = [0u8; ISOLATE_SIZE];
+        for (dst, src) in ret
+            .chunks_exact_mut(4)
+            .zip(self.color.into_iter().chain([self.tolerance]))
+        {
+            dst.copy_from_slice(&(src as u32).to_le_bytes());
+        }
+        ret
+    }
+}
+
+/// Draws the dimming for an [`Isolate`], with the same bind group layouts as the bit-plane viewer
+#[derive(Debug)]
+pub struct ColorIsolation {
+    pipeline: RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl ColorIsolation {
+    pub fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        plane_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("isolate"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/isolate.wgsl"),
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[layout, plane_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("isolate"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_isolate",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("isolate"),
+            mapped_at_creation: false,
+            size: ISOLATE_SIZE as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            pipeline,
+            buffer,
+            bind_group: None,
+        }
+    }
+    /// Point at a (re)loaded image's raw texture, or drop the bind group if there is none
+    pub fn update_bind_group(
+        &mut self,
+        device: &Device,
+        plane_layout: &BindGroupLayout,
+        raw: Option<&wgpu::TextureView>,
+    ) {
+        self.bind_group = raw.map(|view| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: plane_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: self.buffer.as_entire_binding(),
+                    },
+                ],
+                label: None,
+            })
+        });
+    }
+    pub fn write(&self, queue: &Queue, isolate: Isolate) {
+        queue.write_buffer(&self.buffer, 0, &isolate.to_bytes());
+    }
+    /// Dim over a viewport. Expects the bind group at index 0 to already be set
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        let Some(bind_group) = &self.bind_group else {
+            return;
+        };
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(1, bind_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x1: two reds, a blue and a red one step off
+    fn image() -> RawImage {
+        RawImage {
+            width: 4,
+            height: 1,
+            depth: 8,
+            values: vec![
+                255, 0, 0, 255, //
+                0, 0, 255, 255, //
+                255, 0, 0, 255, //
+                254, 0, 0, 255, //
+            ],
+        }
+    }
+
+    #[test]
+    fn unique_colors_most_common_first() {
+        assert_eq!(
+            unique_colors(&image()),
+            [
+                ([255, 0, 0, 255], 2),
+                // ties in color order
+                ([0, 0, 255, 255], 1),
+                ([254, 0, 0, 255], 1),
+            ]
+        );
+        assert_eq!(hex([255, 128, 0, 255], 8), "#ff8000ff");
+        assert_eq!(hex([1, 0, 0, 65535], 16), "#000100000000ffff");
+    }
+
+    #[test]
+    fn isolate_tolerance_is_inclusive() {
+        let isolate = Isolate {
+            color: [255, 0, 0, 255],
+            tolerance: 1,
+        };
+        assert!(isolate.matches(&[254, 0, 0, 255]));
+        assert!(isolate.matches(&[255, 1, 0, 254]));
+        assert!(!isolate.matches(&[253, 0, 0, 255]));
+        assert!(!isolate.matches(&[255, 0, 2, 255]));
+        assert_eq!(isolate.count(&image()), 3);
+        let exact = Isolate {
+            tolerance: 0,
+            ..isolate
+        };
+        assert_eq!(exact.count(&image()), 2);
+    }
+}
@@ -0,0 +1,200 @@
+//! Comparing the image with a second one, for spotting what changed between versions of it. Slot
+//! A is the image, slot B is `SLOT_B_FILE`, moved by a manual offset when the two don't line up
+use std::{fmt::Display, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::raw::RawImage;
+
+/// Watched like the image, so a new version can be dropped in next to the old one
+pub const SLOT_B_FILE: &str = "compare.png";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Mode {
+    /// |A - B| per channel
+    #[default]
+    Absolute,
+    /// A - B per channel around mid-gray
+    Signed,
+    /// A ^ B of the channel values, so the bytes of 16-bit images both take part
+    Xor,
+    /// White where any channel differs, black elsewhere
+    Changed,
+}
+
+impl Mode {
+    pub fn cycle(self) -> Self {
+        match self {
+            Self::Absolute => Self::Signed,
+            Self::Signed => Self::Xor,
+            Self::Xor => Self::Changed,
+            Self::Changed => Self::Absolute,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Absolute => "absolute difference",
+            Self::Signed => "signed difference",
+            Self::Xor => "XOR",
+            Self::Changed => "changed pixels",
+        }
+    }
+}
+
+/// What a comparison found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub mode: Mode,
+    /// Where slot B's top left corner is in slot A, in pixels
+    pub offset: (i32, i32),
+    /// Pixels where any channel differs
+    pub changed: usize,
+    /// Pixels of A that B covers, the only ones compared
+    pub overlap: usize,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at offset ({}, {}), {} of {} pixels changed",
+            self.mode.label(),
+            self.offset.0,
+            self.offset.1,
+            self.changed,
+            self.overlap
+        )
+    }
+}
+
+/// Compare `a` with `b` moved by `offset`, at `a`'s bit depth. Pixels of `a` that `b` doesn't
+/// cover come out transparent
+pub fn compare(a: &RawImage, b: &RawImage, mode: Mode, offset: (i32, i32)) -> (RawImage, Summary) {
+    let max = (1u32 << a.depth) - 1;
+    let rescale = |x: u16| match (a.depth, b.depth) {
+        (16, 8) => x * 257,
+        (8, 16) => x >> 8,
+        _ => x,
+    };
+    let mut values = Vec::with_capacity(a.values.len());
+    let (mut changed, mut overlap) = (0, 0);
+    for y in 0..a.height as i32 {
+        for x in 0..a.width as i32 {
+            let (bx, by) = (x - offset.0, y - offset.1);
+            if bx < 0 || by < 0 || bx >= b.width as i32 || by >= b.height as i32 {
+                values.extend([0; 4]);
+                continue;
+            }
+            let pa = a.pixel(x as u32, y as u32);
+            let pb = b.pixel(bx as u32, by as u32);
+            let pb: [u16; 4] = std::array::from_fn(|c| rescale(pb[c]));
+            let differs = *pa != pb;
+            overlap += 1;
+            changed += differs as usize;
+            let channel = |c: usize| {
+                let (va, vb) = (pa[c] as u32, pb[c] as u32);
+                (match mode {
+                    Mode::Absolute => va.abs_diff(vb),
+                    Mode::Signed => {
+                        let diff = va as i32 - vb as i32;
+                        ((max as i32 + 1) / 2 + diff / 2).clamp(0, max as i32) as u32
+                    }
+                    Mode::Xor => va ^ vb,
+                    Mode::Changed => max * differs as u32,
+                }) as u16
+            };
+            values.extend([channel(0), channel(1), channel(2), max as u16]);
+        }
+    }
+    let raw = RawImage {
+        width: a.width,
+        height: a.height,
+        depth: a.depth,
+        values,
+    };
+    let summary = Summary {
+        mode,
+        offset,
+        changed,
+        overlap,
+    };
+    (raw, summary)
+}
+
+/// A viewport's comparison, drawn through its shader in place of the image
+#[derive(Debug)]
+pub struct Layer {
+    pub summary: Arc<Summary>,
+    /// Like the viewport's own, but with the comparison bound in place of the image
+    pub bind_group: wgpu::BindGroup,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Opaque gray pixels from `values`, `width` wide
+    fn gray(width: u32, depth: u32, values: &[u16]) -> RawImage {
+        RawImage {
+            width,
+            height: values.len() as u32 / width,
+            depth,
+            values: (values.iter())
+                .flat_map(|&x| [x, x, x, ((1u32 << depth) - 1) as u16])
+                .collect(),
+        }
+    }
+
+    /// First channel of every pixel, and alpha to tell what was compared
+    fn red_alpha(raw: &RawImage) -> Vec<(u16, u16)> {
+        raw.values.chunks_exact(4).map(|p| (p[0], p[3])).collect()
+    }
+
+    #[test]
+    fn offset_leaves_uncovered_pixels_transparent() {
+        let a = gray(3, 8, &[10, 20, 30, 40, 50, 60]);
+        let b = gray(2, 8, &[20, 35]);
+        // B's corner on A's middle pixel of the top row
+        let (raw, summary) = compare(&a, &b, Mode::Absolute, (1, 0));
+        assert_eq!(
+            red_alpha(&raw),
+            [(0, 0), (0, 255), (5, 255), (0, 0), (0, 0), (0, 0)]
+        );
+        assert_eq!((summary.changed, summary.overlap), (1, 2));
+        // and hanging off the top left
+        let (raw, summary) = compare(&a, &b, Mode::Absolute, (-1, 0));
+        assert_eq!(red_alpha(&raw)[..3], [(25, 255), (0, 0), (0, 0)]);
+        assert_eq!((summary.changed, summary.overlap), (1, 1));
+        assert_eq!(
+            summary.to_string(),
+            "absolute difference at offset (-1, 0), 1 of 1 pixels changed"
+        );
+    }
+
+    #[test]
+    fn modes() {
+        let a = gray(3, 8, &[100, 0b1100, 7]);
+        let b = gray(3, 8, &[100, 0b1010, 9]);
+        let red = |mode| red_alpha(&compare(&a, &b, mode, (0, 0)).0);
+        assert_eq!(red(Mode::Absolute), [(0, 255), (2, 255), (2, 255)]);
+        // equal pixels are mid-gray, differences are halved either side of it
+        assert_eq!(red(Mode::Signed), [(128, 255), (129, 255), (127, 255)]);
+        assert_eq!(red(Mode::Xor), [(0, 255), (0b0110, 255), (0b1110, 255)]);
+        assert_eq!(red(Mode::Changed), [(0, 255), (255, 255), (255, 255)]);
+    }
+
+    #[test]
+    fn depths_are_rescaled_to_a() {
+        let a8 = gray(2, 8, &[255, 1]);
+        let b16 = gray(2, 16, &[65535, 0x01ff]);
+        // 16-bit B drops its low byte to match an 8-bit A
+        let (raw, summary) = compare(&a8, &b16, Mode::Absolute, (0, 0));
+        assert_eq!(red_alpha(&raw), [(0, 255), (0, 255)]);
+        assert_eq!(summary.changed, 0);
+        // 8-bit B spreads over the whole 16-bit range
+        let (raw, summary) = compare(&b16, &a8, Mode::Signed, (0, 0));
+        assert_eq!(raw.depth, 16);
+        assert_eq!(red_alpha(&raw), [(32768, 65535), (32768 + 127, 65535)]);
+        assert_eq!(summary.changed, 1);
+    }
+}
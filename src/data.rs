@@ -0,0 +1,70 @@
+//! CPU side of the `Data` uniform every shader gets at binding 3
+
+/// Size of the `Data` uniform, must be a multiple of 16 bytes
+pub const DATA_SIZE: usize = 96;
+
+/// Mirrors `Data` in shader.wgsl and src/shaders/common.wgsl, field for field
+#[derive(Debug, Clone, Default)]
+pub struct Data {
+    pub img_dim: (f32, f32),
+    /// Size of the viewport being drawn into
+    pub win_dim: (f32, f32),
+    pub pos: (f32, f32),
+    pub scale: f32,
+    pub lod_bias: f32,
+    pub flip: (f32, f32),
+    pub rotation: f32,
+    pub wipe_mode: f32,
+    pub wipe: (f32, f32),
+    pub wipe_radius: f32,
+    pub wipe_swap: f32,
+    pub background: [f32; 4],
+    pub checkerboard: f32,
+    pub stereo_period: f32,
+    pub stereo_range: f32,
+}
+
+impl Data {
+    /// View that exactly fills a target the size of the image, for offscreen renders
+    pub fn identity(img_dim: (f32, f32), lod_bias: f32) -> Self {
+        Self {
+            img_dim,
+            win_dim: img_dim,
+            scale: 1.0,
+            lod_bias,
+            flip: (1.0, 1.0),
+            ..Default::default()
+        }
+    }
+    pub fn to_bytes(&self) -> [u8; DATA_SIZE] {
+        let mut ret = [0u8; DATA_SIZE];
+        for (dst, src) in ret.chunks_exact_mut(4).zip([
+            self.img_dim.0,
+            self.img_dim.1,
+            self.win_dim.0,
+            self.win_dim.1,
+            self.pos.0,
+            self.pos.1,
+            self.scale,
+            self.lod_bias,
+            self.flip.0,
+            self.flip.1,
+            self.rotation,
+            self.wipe_mode,
+            self.wipe.0,
+            self.wipe.1,
+            self.wipe_radius,
+            self.wipe_swap,
+            self.background[0],
+            self.background[1],
+            self.background[2],
+            self.background[3],
+            self.checkerboard,
+            self.stereo_period,
+            self.stereo_range,
+        ]) {
+            dst.copy_from_slice(&src.to_le_bytes());
+        }
+        ret
+    }
+}
@@ -0,0 +1,169 @@
+//! JPEG error level analysis: save the image again as a JPEG at a known quality and amplify how
+//! much each pixel changed. Regions pasted in after the last save recompress differently from the
+//! rest. The difference is uploaded like the image, and viewports can show it through their
+//! shader or over it
+use std::{borrow::Cow, io::Cursor};
+
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageError, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroupLayout, Device, RenderPipeline};
+
+use crate::raw::RawImage;
+
+/// How a viewport uses the ELA image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Mode {
+    #[default]
+    Off,
+    /// Bound in place of the image, so the viewport's shader sees the difference
+    View,
+    /// Drawn over the shader output, brighter where the difference is bigger
+    Overlay,
+}
+
+impl Mode {
+    pub fn cycle(self) -> Self {
+        match self {
+            Self::Off => Self::View,
+            Self::View => Self::Overlay,
+            Self::Overlay => Self::Off,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::View => "view",
+            Self::Overlay => "overlay",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// JPEG quality to recompress at, 1-100
+    pub quality: u8,
+    /// What the absolute difference is multiplied by
+    pub scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            quality: 90,
+            scale: 20.0,
+        }
+    }
+}
+
+/// Scaled absolute difference between the image and itself recompressed, opaque
+pub fn ela(raw: &RawImage, settings: Settings) -> Result<DynamicImage, ImageError> {
+    let shift = raw.depth - 8;
+    let rgb: Vec<u8> = raw
+        .values
+        .chunks_exact(4)
+        .flat_map(|pixel| pixel[..3].iter().map(|&x| (x >> shift) as u8))
+        .collect();
+    let rgb = RgbImage::from_raw(raw.width, raw.height, rgb).unwrap();
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(Cursor::new(&mut jpeg), settings.quality).encode_image(&rgb)?;
+    let recompressed = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg)?;
+    let recompressed = recompressed.to_rgb8();
+    let diff = RgbaImage::from_fn(raw.width, raw.height, |x, y| {
+        let (a, b) = (rgb.get_pixel(x, y), recompressed.get_pixel(x, y));
+        let channel = |c: usize| {
+            (a[c].abs_diff(b[c]) as f32 * settings.scale)
+                .round()
+                .min(255.0) as u8
+        };
+        image::Rgba([channel(0), channel(1), channel(2), 255])
+    });
+    Ok(DynamicImage::ImageRgba8(diff))
+}
+
+/// Draws the ELA image over a viewport
+#[derive(Debug)]
+pub struct ElaOverlay {
+    pipeline: RenderPipeline,
+}
+
+impl ElaOverlay {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ela"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/ela.wgsl"),
+            ))),
+        });
+        Self {
+            pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_main",
+                "fs_ela_overlay",
+                format,
+                Some(wgpu::BlendState::ALPHA_BLENDING),
+            ),
+        }
+    }
+    /// Draw over whatever is there, with the ELA image bound as the viewport's texture
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass, ela_group: &wgpu::BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, ela_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16x16 8-bit image with every pixel from `f`
+    fn image(f: impl Fn(u32, u32) -> [u16; 3]) -> RawImage {
+        RawImage {
+            width: 16,
+            height: 16,
+            depth: 8,
+            values: (0..16 * 16)
+                .flat_map(|i| {
+                    let [r, g, b] = f(i % 16, i / 16);
+                    [r, g, b, 255]
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn flat_image_barely_changes() {
+        let settings = Settings {
+            quality: 90,
+            scale: 1.0,
+        };
+        let diff = ela(&image(|_, _| [90, 140, 200]), settings).unwrap();
+        for pixel in diff.to_rgba8().pixels() {
+            // only the rounding going to YCbCr and back is left
+            assert!(pixel.0[..3].iter().all(|&x| x <= 2), "{pixel:?}");
+            assert_eq!(pixel[3], 255);
+        }
+    }
+
+    #[test]
+    fn sixteen_bit_uses_top_byte() {
+        let gradient = |x: u32, y: u32| [(x * 16) as u16, (y * 16) as u16, ((x ^ y) * 16) as u16];
+        let eight = image(gradient);
+        // same top bytes, with noise in the bottom ones that the JPEG never sees
+        let sixteen = RawImage {
+            depth: 16,
+            values: (eight.values.iter().enumerate())
+                .map(|(i, &x)| x << 8 | (i as u16 * 37) & 0xff)
+                .collect(),
+            ..image(gradient)
+        };
+        let settings = Settings::default();
+        assert_eq!(
+            ela(&sixteen, settings).unwrap().to_rgba8(),
+            ela(&eight, settings).unwrap().to_rgba8()
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Rendering user shaders offscreen at the image's own resolution, and reading the result back
+use std::{
+    error::Error,
+    future::Future,
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+use wgpu::{Device, Queue, RenderPipeline};
+
+use crate::{data::Data, viewport::Viewport};
+
+/// Exports are rendered in the color space the swapchain usually has, so they look like the
+/// window does
+pub const EXPORT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// For analysis shaders whose output shouldn't be clamped to 8 bits. Values are written as the
+/// shader returns them, without any sRGB conversion
+pub const FLOAT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+/// What floats are rendered into where Rgba32Float isn't renderable, like GL on llvmpipe
+pub const HALF_FLOAT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Files that keep every channel as a 32-bit float
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatFormat {
+    Exr,
+    /// NumPy array of shape (height, width, 4)
+    Npy,
+    /// Little-endian RGBA rows, top row first, with a JSON sidecar saying how big it is
+    Raw,
+}
+
+impl FloatFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match Path::new(name).extension()?.to_str()? {
+            "exr" => Some(Self::Exr),
+            "npy" => Some(Self::Npy),
+            "f32" | "raw" => Some(Self::Raw),
+            _ => None,
+        }
+    }
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Exr => "exr",
+            Self::Npy => "npy",
+            Self::Raw => "f32",
+        }
+    }
+    pub fn cycle(self) -> Self {
+        match self {
+            Self::Exr => Self::Npy,
+            Self::Npy => Self::Raw,
+            Self::Raw => Self::Exr,
+        }
+    }
+}
+
+/// Format to render into to save the result as `name`
+pub fn target_format(adapter: &wgpu::Adapter, name: &str) -> wgpu::TextureFormat {
+    if FloatFormat::from_name(name).is_none() {
+        return EXPORT_FORMAT;
+    }
+    let features = adapter.get_texture_format_features(FLOAT_FORMAT);
+    if features
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    {
+        FLOAT_FORMAT
+    } else {
+        log::warn!("can't render to {FLOAT_FORMAT:?} here, floats will only have half precision");
+        HALF_FLOAT_FORMAT
+    }
+}
+
+/// Draw one viewport's shader into a new `size` texture, with whatever view `data` describes.
+/// Returns `None` if no image is loaded yet
+pub fn render_offscreen(
+    device: &Device,
+    queue: &Queue,
+    pipeline: &RenderPipeline,
+    viewport: &Viewport,
+    data: &Data,
+    (width, height): (u32, u32),
+    format: wgpu::TextureFormat,
+) -> Option<wgpu::Texture> {
+    let group = viewport.bind_group.as_ref()?;
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("export"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        // read back for exports, bound for the shader output histogram
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    queue.write_buffer(&viewport.data_buffer, 0, &data.to_bytes());
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("export"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    rpass.set_pipeline(pipeline);
+    rpass.set_bind_group(0, group, &[]);
+    rpass.draw(0..4, 0..1);
+    drop(rpass);
+    queue.submit(Some(encoder.finish()));
+    Some(texture)
+}
+
+/// Copy a texture back to the CPU. Resolves to its pixels, row by row without padding
+pub fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    texture: &wgpu::Texture,
+) -> impl 'static + Future<Output = Result<Vec<u8>, wgpu::BufferAsyncError>> {
+    let pixel_size = texture.format().block_copy_size(None).unwrap_or(4);
+    let row = texture.width() * pixel_size;
+    let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let height = texture.height();
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback"),
+        size: (padded_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+    let mapped = map_buffer(device, buffer);
+    async move {
+        let buffer = mapped.await?;
+        let mapped = buffer.slice(..).get_mapped_range();
+        let pixels = mapped
+            .chunks_exact(padded_row as usize)
+            .flat_map(|padded| &padded[..row as usize])
+            .copied()
+            .collect();
+        drop(mapped);
+        buffer.unmap();
+        Ok(pixels)
+    }
+}
+
+/// Map a `MAP_READ` buffer once the GPU is done with it. Resolves to the same buffer, mapped
+pub fn map_buffer(
+    device: &Device,
+    buffer: wgpu::Buffer,
+) -> impl 'static + Future<Output = Result<wgpu::Buffer, wgpu::BufferAsyncError>> {
+    // the callback may run on another thread, or (on the web) long after we return
+    let state = Arc::new(Mutex::new((None, None::<Waker>)));
+    let state1 = state.clone();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let mut state = state1.lock().unwrap();
+            state.0 = Some(result);
+            if let Some(waker) = state.1.take() {
+                waker.wake();
+            }
+        });
+    // blocks until the copy is done on native, does nothing on the web
+    device.poll(wgpu::Maintain::Wait);
+
+    async move {
+        std::future::poll_fn(|cx| {
+            let mut state = state.lock().unwrap();
+            match state.0.take() {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    state.1 = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await?;
+        Ok(buffer)
+    }
+}
+
+/// Pixels read back from a texture in a window's format, as opaque RGBA the way the window
+/// shows them
+pub fn window_rgba(format: wgpu::TextureFormat, mut pixels: Vec<u8>) -> Vec<u8> {
+    let bgra = matches!(
+        format,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    );
+    for pixel in pixels.chunks_exact_mut(4) {
+        if bgra {
+            pixel.swap(0, 2);
+        }
+        pixel[3] = 255;
+    }
+    pixels
+}
+
+/// Name and contents
+pub type File = (String, Vec<u8>);
+
+/// Encode pixels read back from a [`target_format`] texture as whatever `name`'s extension
+/// asks for. Returns every file to write with its name, since raw floats get a sidecar
+pub fn encode(
+    name: &str,
+    texture_format: wgpu::TextureFormat,
+    size: (u32, u32),
+    pixels: Vec<u8>,
+) -> Result<Vec<File>, Box<dyn Error>> {
+    let Some(format) = FloatFormat::from_name(name) else {
+        return Ok(vec![(name.to_owned(), encode_png(size, pixels)?)]);
+    };
+    let floats: Vec<f32> = match texture_format {
+        HALF_FLOAT_FORMAT => pixels
+            .chunks_exact(2)
+            .map(|x| half::f16::from_ne_bytes(x.try_into().unwrap()).to_f32())
+            .collect(),
+        _ => pixels
+            .chunks_exact(4)
+            .map(|x| f32::from_ne_bytes(x.try_into().unwrap()))
+            .collect(),
+    };
+    let le_bytes = || floats.iter().flat_map(|x| x.to_le_bytes());
+    let (width, height) = size;
+    Ok(match format {
+        FloatFormat::Exr => {
+            let img = image::Rgba32FImage::from_raw(width, height, floats)
+                .ok_or("pixel count doesn't match the size")?;
+            let mut exr = Vec::new();
+            img.write_to(
+                &mut std::io::Cursor::new(&mut exr),
+                image::ImageFormat::OpenExr,
+            )?;
+            vec![(name.to_owned(), exr)]
+        }
+        FloatFormat::Npy => {
+            let mut header = format!(
+                "{{'descr': '<f4', 'fortran_order': False, 'shape': ({height}, {width}, 4), }}"
+            );
+            // magic, version and length come first, and the data has to start 64-byte aligned
+            while (10 + header.len() + 1) % 64 != 0 {
+                header.push(' ');
+            }
+            header.push('\n');
+            let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+            npy.extend((header.len() as u16).to_le_bytes());
+            npy.extend(header.bytes());
+            npy.extend(le_bytes());
+            vec![(name.to_owned(), npy)]
+        }
+        FloatFormat::Raw => {
+            let sidecar = Path::new(name).with_extension("json");
+            let json = format!(
+                "{{\"width\": {width}, \"height\": {height}, \"channels\": 4, \
+                 \"dtype\": \"float32\", \"endianness\": \"little\", \
+                 \"layout\": \"rgba, row-major, top row first\"}}\n"
+            );
+            vec![
+                (name.to_owned(), le_bytes().collect()),
+                (sidecar.to_string_lossy().into_owned(), json.into_bytes()),
+            ]
+        }
+    })
+}
+
+pub fn encode_png(
+    (width, height): (u32, u32),
+    rgba: Vec<u8>,
+) -> Result<Vec<u8>, image::ImageError> {
+    let img = image::RgbaImage::from_raw(width, height, rgba).ok_or_else(|| {
+        image::ImageError::Parameter(image::error::ParameterError::from_kind(
+            image::error::ParameterErrorKind::DimensionMismatch,
+        ))
+    })?;
+    let mut png = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(png)
+}
@@ -0,0 +1,188 @@
+//! Bytes hidden in an image, picked out by a shader:
+//!
+//! ```text
+//! graphics-toolbox extract --image clue.png --shader lsb.wgsl --channels rgb -o payload.bin
+//! ```
+//!
+//! See [`crate::bits`] for what the shader has to output. Without `-o`, or with `--hex`, the
+//! bytes are printed as hex and ASCII instead.
+//!
+//! Plain low bits don't need a shader, `lsb` reads them from the image itself:
+//!
+//! ```text
+//! graphics-toolbox lsb --image clue.png --spec b1,bgr,lsb,yx -o payload.bin
+//! ```
+//!
+//! See [`crate::lsb`] for how specs are written. `scan` tries them all and lists what looks
+//! like text or a file, best first.
+use std::{collections::HashMap, error::Error};
+
+use crate::{
+    bits::{self, Packing, BITS_FORMAT},
+    headless::{number, value, Headless},
+    lsb::{self, Spec},
+    raw::RawImage,
+    steg,
+};
+
+#[derive(Debug, Default)]
+pub struct ExtractArgs {
+    image: String,
+    shader: String,
+    output: Option<String>,
+    packing: Packing,
+    hex: bool,
+    fallback: bool,
+}
+
+impl ExtractArgs {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut ret = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--image" | "-i" => ret.image = value(&mut args, &arg)?,
+                "--shader" | "-s" => ret.shader = value(&mut args, &arg)?,
+                "--output" | "-o" => ret.output = Some(value(&mut args, &arg)?),
+                "--channels" | "-c" => {
+                    ret.packing.channels = bits::parse_channels(&value(&mut args, &arg)?)?;
+                }
+                "--bit-order" => {
+                    ret.packing.msb_first = match value(&mut args, &arg)?.as_str() {
+                        "msb" => true,
+                        "lsb" => false,
+                        other => return Err(format!("bit order {other} isn't msb or lsb")),
+                    };
+                }
+                "--scan" => {
+                    ret.packing.column_major = match value(&mut args, &arg)?.as_str() {
+                        "rows" => false,
+                        "columns" => true,
+                        other => return Err(format!("scan {other} isn't rows or columns")),
+                    };
+                }
+                "--word" => ret.packing.word = number(&mut args, &arg)?,
+                "--byte-order" => {
+                    ret.packing.little_endian = match value(&mut args, &arg)?.as_str() {
+                        "big" => false,
+                        "little" => true,
+                        other => return Err(format!("byte order {other} isn't big or little")),
+                    };
+                }
+                "--hex" => ret.hex = true,
+                "--fallback" => ret.fallback = true,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if ret.image.is_empty() || ret.shader.is_empty() {
+            return Err("--image and --shader are required".to_owned());
+        }
+        if ret.packing.channels.is_empty() || ret.packing.word == 0 {
+            return Err("--channels and --word can't be empty".to_owned());
+        }
+        Ok(ret)
+    }
+}
+
+pub fn run(args: ExtractArgs) -> Result<(), Box<dyn Error>> {
+    let img = image::open(&args.image)?;
+    let code = std::fs::read_to_string(&args.shader)?;
+    let size = (img.width(), img.height());
+    let headless = pollster::block_on(Headless::new(args.fallback))?;
+    let pixels = pollster::block_on(async {
+        let pipeline = headless
+            .pipeline(&args.shader, &code, BITS_FORMAT, &HashMap::new())
+            .await?;
+        headless.render(&pipeline, img, BITS_FORMAT).await
+    })?;
+    let (bytes, count) = bits::extract(&pixels, size, &args.packing);
+    eprintln!("{count} bits, {} bytes", bytes.len());
+    if args.hex || args.output.is_none() {
+        print!("{}", bits::hexdump(&bytes, usize::MAX));
+    }
+    if let Some(output) = &args.output {
+        std::fs::write(output, &bytes)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct LsbArgs {
+    image: String,
+    output: Option<String>,
+    spec: Spec,
+    hex: bool,
+}
+
+impl LsbArgs {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut ret = Self::default();
+        let (mut step, mut offset) = (None, None);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--image" | "-i" => ret.image = value(&mut args, &arg)?,
+                "--output" | "-o" => ret.output = Some(value(&mut args, &arg)?),
+                "--spec" => ret.spec = value(&mut args, &arg)?.parse()?,
+                "--step" => step = Some(number::<usize>(&mut args, &arg)?.max(1)),
+                "--offset" => offset = Some(number(&mut args, &arg)?),
+                "--hex" => ret.hex = true,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if ret.image.is_empty() {
+            return Err("--image is required".to_owned());
+        }
+        // the flags win over the spec, wherever they were given
+        ret.spec.step = step.unwrap_or(ret.spec.step);
+        ret.spec.offset = offset.unwrap_or(ret.spec.offset);
+        Ok(ret)
+    }
+}
+
+pub fn run_lsb(args: LsbArgs) -> Result<(), Box<dyn Error>> {
+    let raw = RawImage::new(&image::open(&args.image)?);
+    let bytes = lsb::extract(&raw, &args.spec, usize::MAX);
+    eprintln!("{}: {} bytes", args.spec, bytes.len());
+    if args.hex || args.output.is_none() {
+        print!("{}", lsb::preview(&bytes, usize::MAX));
+    }
+    if let Some(output) = &args.output {
+        std::fs::write(output, &bytes)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct ScanArgs {
+    image: String,
+    top: usize,
+}
+
+impl ScanArgs {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut ret = Self {
+            image: String::new(),
+            top: 20,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--image" | "-i" => ret.image = value(&mut args, &arg)?,
+                "--top" => ret.top = number(&mut args, &arg)?,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if ret.image.is_empty() {
+            return Err("--image is required".to_owned());
+        }
+        Ok(ret)
+    }
+}
+
+pub fn run_scan(args: ScanArgs) -> Result<(), Box<dyn Error>> {
+    let raw = RawImage::new(&image::open(&args.image)?);
+    let hits = steg::scan(&raw);
+    eprintln!("{} hits in {} specs", hits.len(), steg::specs().len());
+    for hit in hits.iter().take(args.top) {
+        println!("{hit}");
+    }
+    Ok(())
+}
@@ -0,0 +1,322 @@
+//! Frequency-domain filtering: the image's spectrum times a mask, transformed back. The mask is
+//! the product of the shapes in `FILTER_FILE`, notches painted onto a spectrum view with the right
+//! mouse button, and optionally a user shader's red channel. The result is bound at binding 4 so
+//! user shaders can sample it
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+use rustfft::FftPlanner;
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroupLayout, Device, RenderPipeline};
+
+use crate::spectrum::{self, Spectrum};
+
+/// Edited by hand like `lsb::SPEC_FILE`, applied whenever it changes
+pub const FILTER_FILE: &str = "filter.txt";
+
+/// Frequencies are in cycles per image, measured from the centre of the spectrum
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    /// Keep frequencies up to this far from zero
+    LowPass(f32),
+    /// Keep frequencies further than this from zero
+    HighPass(f32),
+    /// Keep frequencies between the two distances from zero
+    BandPass(f32, f32),
+    /// Take out a disc around a frequency, and around its mirror image since the spectrum of a
+    /// real image is symmetric
+    Notch { fx: f32, fy: f32, r: f32 },
+}
+
+impl Shape {
+    pub fn keeps(&self, (fx, fy): (f32, f32)) -> bool {
+        let distance = fx.hypot(fy);
+        match *self {
+            Self::LowPass(r) => distance <= r,
+            Self::HighPass(r) => distance > r,
+            Self::BandPass(r0, r1) => (r0..=r1).contains(&distance),
+            Self::Notch { fx: cx, fy: cy, r } => {
+                (fx - cx).hypot(fy - cy) > r && (fx + cx).hypot(fy + cy) > r
+            }
+        }
+    }
+}
+
+impl Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LowPass(r) => write!(f, "lowpass {r}"),
+            Self::HighPass(r) => write!(f, "highpass {r}"),
+            Self::BandPass(r0, r1) => write!(f, "bandpass {r0} {r1}"),
+            Self::Notch { fx, fy, r } => write!(f, "notch {fx} {fy} {r}"),
+        }
+    }
+}
+
+/// What `FILTER_FILE` says, one item per line:
+/// `lowpass R`, `highpass R`, `bandpass R0 R1`, `notch FX FY R`, `shader NAME.wgsl` and
+/// `brush R`. Lines starting with `#` are comments
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    pub shapes: Vec<Shape>,
+    /// User shader whose red output, rendered over the centred spectrum, multiplies the mask
+    pub shader: Option<String>,
+    /// Radius of painted notches
+    pub brush: f32,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            shapes: Vec::new(),
+            shader: None,
+            brush: 3.0,
+        }
+    }
+}
+
+impl Display for Spec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for shape in &self.shapes {
+            writeln!(f, "{shape}")?;
+        }
+        if let Some(shader) = &self.shader {
+            writeln!(f, "shader {shader}")?;
+        }
+        write!(f, "brush {}", self.brush)
+    }
+}
+
+impl FromStr for Spec {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::default();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let kind = tokens.next().unwrap_or_default();
+            let rest: Vec<_> = tokens.collect();
+            let numbers = || {
+                rest.iter()
+                    .map(|token| {
+                        token
+                            .parse::<f32>()
+                            .map_err(|err| format!("{token}: {err}"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            match (kind, rest.len()) {
+                ("shader", 1) => ret.shader = Some(rest[0].to_owned()),
+                ("brush", 1) => ret.brush = numbers()?[0],
+                ("lowpass", 1) => ret.shapes.push(Shape::LowPass(numbers()?[0])),
+                ("highpass", 1) => ret.shapes.push(Shape::HighPass(numbers()?[0])),
+                ("bandpass", 2) => {
+                    let n = numbers()?;
+                    ret.shapes.push(Shape::BandPass(n[0], n[1]));
+                }
+                ("notch", 3) => {
+                    let n = numbers()?;
+                    ret.shapes.push(Shape::Notch {
+                        fx: n[0],
+                        fy: n[1],
+                        r: n[2],
+                    });
+                }
+                _ => return Err(format!("can't understand {line:?}")),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Centred mask over a `width`×`height` spectrum, 1 where frequencies are kept. `shader` is the
+/// mask shader's red channel, ignored if it was rendered at some other size
+pub fn mask(
+    spec: &Spec,
+    painted: &[Shape],
+    shader: Option<&[f32]>,
+    (width, height): (u32, u32),
+) -> Vec<f32> {
+    let dim = (width as f32, height as f32);
+    let mut ret = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let f = spectrum::frequency((x as f32, y as f32), dim);
+            let keep = spec
+                .shapes
+                .iter()
+                .chain(painted)
+                .all(|shape| shape.keeps(f));
+            ret.push(if keep { 1.0 } else { 0.0 });
+        }
+    }
+    if let Some(shader) = shader.filter(|shader| shader.len() == ret.len()) {
+        for (value, factor) in ret.iter_mut().zip(shader) {
+            *value *= factor;
+        }
+    }
+    ret
+}
+
+/// Multiply R, G and B of the spectrum by a centred mask and transform back to RGBA pixels. Alpha
+/// is left alone so a high-pass doesn't make everything transparent. Values can ring a little
+/// outside 0-1
+pub fn apply(spectrum: &Spectrum, mask: &[f32]) -> Vec<[f32; 4]> {
+    let (width, height) = (spectrum.width as usize, spectrum.height as usize);
+    let mut planner = FftPlanner::new();
+    let mut ret = vec![[0.0; 4]; width * height];
+    for (c, channel) in spectrum.channels[..4].iter().enumerate() {
+        let mut data = channel.clone();
+        if c < 3 {
+            for y in 0..height {
+                let row = spectrum::uncentre(y, height) * width;
+                for x in 0..width {
+                    data[row + spectrum::uncentre(x, width)] *= mask[y * width + x];
+                }
+            }
+        }
+        spectrum::fft_2d(&mut planner, &mut data, width, true);
+        let n = data.len() as f32;
+        for (pixel, z) in ret.iter_mut().zip(data) {
+            pixel[c] = z.re / n;
+        }
+    }
+    ret
+}
+
+/// Shows the filter result in a viewport, drawn instead of the user shader
+#[derive(Debug)]
+pub struct ResultViewer {
+    pipeline: RenderPipeline,
+}
+
+impl ResultViewer {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("filter result"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/filter.wgsl"),
+            ))),
+        });
+        Self {
+            pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_main",
+                "fs_result",
+                format,
+                Some(wgpu::BlendState::ALPHA_BLENDING),
+            ),
+        }
+    }
+    /// Expects the viewport's bind group at index 0 to already be set
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use super::*;
+    use crate::raw::RawImage;
+
+    const DIM: (u32, u32) = (16, 8);
+
+    /// Gray 16-bit image with one cycle across it plus six faster ones
+    fn two_cosines() -> RawImage {
+        let values = (0..DIM.0 * DIM.1)
+            .flat_map(|i| {
+                let x = (i % DIM.0) as f32 / DIM.0 as f32;
+                let value = 0.5 + 0.25 * (TAU * x).cos() + 0.2 * (TAU * 6.0 * x).cos();
+                let value = (value * 65535.0).round() as u16;
+                [value, value, value, 65535]
+            })
+            .collect();
+        RawImage {
+            width: DIM.0,
+            height: DIM.1,
+            depth: 16,
+            values,
+        }
+    }
+
+    /// Filter the test image with `spec`, and check every pixel's red against `expected`
+    fn assert_filtered(spec: &str, expected: impl Fn(f32) -> f32) {
+        let spec: Spec = spec.parse().unwrap();
+        let result = apply(&Spectrum::new(&two_cosines()), &mask(&spec, &[], None, DIM));
+        for (i, pixel) in result.iter().enumerate() {
+            let x = (i as u32 % DIM.0) as f32 / DIM.0 as f32;
+            let want = expected(x);
+            assert!(
+                (pixel[0] - want).abs() < 1e-3,
+                "{i}: {} vs {want}",
+                pixel[0]
+            );
+            assert!((pixel[3] - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn all_pass_returns_image() {
+        let raw = two_cosines();
+        assert!(mask(&Spec::default(), &[], None, DIM)
+            .iter()
+            .all(|&value| value == 1.0));
+        assert_filtered("", |x| {
+            let i = (x * DIM.0 as f32).round() as usize * 4;
+            raw.values[i] as f32 / 65535.0
+        });
+    }
+
+    #[test]
+    fn low_pass_removes_fast_cosine() {
+        assert_filtered("lowpass 3", |x| 0.5 + 0.25 * (TAU * x).cos());
+        assert_filtered("highpass 3", |x| 0.2 * (TAU * 6.0 * x).cos());
+    }
+
+    #[test]
+    fn notch_removes_mirrored_frequency_too() {
+        // the fast cosine is at both +6 and -6 cycles across
+        assert_filtered("notch 6 0 1", |x| 0.5 + 0.25 * (TAU * x).cos());
+        // a painted notch is the same, down to the pixel under the cursor and its mirror
+        let dim = (5, 3);
+        let f = spectrum::frequency((4.5, 0.5), (5.0, 3.0));
+        assert_eq!(f, (2.0, -1.0));
+        let notch = Shape::Notch {
+            fx: f.0,
+            fy: f.1,
+            r: 0.5,
+        };
+        let mask = mask(&Spec::default(), &[notch], None, dim);
+        let removed: Vec<_> = (0..mask.len()).filter(|&i| mask[i] == 0.0).collect();
+        // (4, 0) and (0, 2)
+        assert_eq!(removed, [4, 10]);
+    }
+
+    #[test]
+    fn spec_rejects_malformed_lines() {
+        let spec: Spec = "# comment\n\nlowpass 20\nnotch 3 -4 2\nshader mask.wgsl\nbrush 5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            spec.to_string(),
+            "lowpass 20\nnotch 3 -4 2\nshader mask.wgsl\nbrush 5"
+        );
+        assert_eq!(spec.to_string().parse::<Spec>(), Ok(spec));
+        for bad in [
+            "lowpass",
+            "lowpass wide",
+            "notch 3 4",
+            "bandpass 1 2 3",
+            "blur 2",
+        ] {
+            assert!(bad.parse::<Spec>().is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! GPU state shared by every window, and by headless rendering which has no windows at all
+use std::{borrow::Cow, sync::Arc};
+
+use image::GenericImageView;
+use wgpu::{Adapter, BindGroupLayout, Device, Queue};
+
+use crate::{
+    bitplane,
+    histogram::HistogramCounter,
+    mipmap::{self, MipmapGenerator},
+    spectrum::{self, Spectrum},
+    view::View,
+    viewport::Viewport,
+    IMAGE_FORMAT,
+};
+
+#[derive(Debug)]
+pub struct Gpu {
+    pub instance: wgpu::Instance,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+    pub layout: BindGroupLayout,
+    pub samplers: [wgpu::Sampler; 2],
+    /// Binding 4 of `layout`: what the last frequency filter produced, or a transparent pixel
+    pub result: wgpu::TextureView,
+    /// Binding 5 of `layout`: the image with its tiles put back in order, or a transparent pixel
+    pub tiles: wgpu::TextureView,
+    /// Group 1 of the built-in bit-plane viewer
+    pub plane_layout: BindGroupLayout,
+    /// `None` without compute shaders, like on WebGL
+    pub histogram_counter: Option<HistogramCounter>,
+    mipmap_generator: MipmapGenerator,
+}
+
+impl Gpu {
+    /// `None` if there is no adapter that fits
+    pub async fn new(
+        instance: wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface<'_>>,
+        force_fallback_adapter: bool,
+    ) -> Option<Self> {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                // a software rasterizer like llvmpipe, for machines without a GPU
+                force_fallback_adapter,
+                // Request an adapter which can render to our surface, if we have one
+                compatible_surface,
+            })
+            .await?;
+
+        // compute shaders need more than WebGL's limits, so ask for them where they're supported
+        let compute = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        let limits = match compute {
+            true => wgpu::Limits::downlevel_defaults(),
+            false => wgpu::Limits::downlevel_webgl2_defaults(),
+        };
+        // Create the logical device and command queue
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    // lets GL render to Rgba32Float for float exports, where the driver can
+                    required_features: adapter.features()
+                        & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                    // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
+                    required_limits: limits.using_resolution(adapter.limits()),
+                    memory_hints: wgpu::MemoryHints::MemoryUsage,
+                },
+                None,
+            )
+            .await
+            .expect("Failed to create device");
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+            label: None,
+        });
+        let samplers = [
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Nearest,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            }),
+        ];
+        let result = spectrum::float_texture(&device, &queue, "result", (1, 1), &[[0.0; 4]])
+            .create_view(&Default::default());
+        let tiles = spectrum::float_texture(&device, &queue, "tiles", (1, 1), &[[0.0; 4]])
+            .create_view(&Default::default());
+        let plane_layout = bitplane::bind_group_layout(&device);
+        let histogram_counter = compute.then(|| HistogramCounter::new(&device));
+        let mipmap_generator = MipmapGenerator::new(&device, IMAGE_FORMAT);
+        Some(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            layout,
+            samplers,
+            result,
+            tiles,
+            plane_layout,
+            histogram_counter,
+            mipmap_generator,
+        })
+    }
+    /// Upload an image with a full mip chain
+    pub fn create_image_texture(&self, img: image::DynamicImage) -> wgpu::Texture {
+        let dimensions = img.dimensions();
+        let rgba = img.into_rgba8();
+
+        let size = wgpu::Extent3d {
+            width: dimensions.0,
+            height: dimensions.1,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: mipmap::mip_level_count(dimensions.0, dimensions.1),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: IMAGE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &rgba,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * dimensions.0),
+                rows_per_image: Some(dimensions.1),
+            },
+            size,
+        );
+        self.mipmap_generator
+            .generate(&self.device, &self.queue, &texture);
+        texture
+    }
+    pub fn create_shader(&self, name: &str, code: &str) -> wgpu::ShaderModule {
+        self.device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(name),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(code)),
+            })
+    }
+    pub fn new_viewport(
+        &self,
+        view: View,
+        shader: String,
+        texture: Option<&wgpu::TextureView>,
+        raw: Option<&wgpu::TextureView>,
+    ) -> Viewport {
+        let mut viewport = Viewport::new(&self.device, view, shader);
+        viewport.update_bind_group(
+            &self.device,
+            &self.layout,
+            texture,
+            &self.samplers,
+            &self.result,
+            &self.tiles,
+        );
+        viewport.update_plane_bind_group(&self.device, &self.plane_layout, raw);
+        viewport
+    }
+    /// Show part of a spectrum in a viewport instead of the image, with the same view
+    pub fn show_spectrum(
+        &self,
+        viewport: &mut Viewport,
+        spectrum: Arc<Spectrum>,
+        shown: spectrum::Shown,
+        output: bool,
+        mask: Option<&[f32]>,
+    ) {
+        let texture = spectrum.texture(&self.device, &self.queue, shown, mask);
+        let view = texture.create_view(&Default::default());
+        viewport.spectrum = Some(spectrum::Layer {
+            bind_group: viewport.create_bind_group(
+                &self.device,
+                &self.layout,
+                &view,
+                &self.samplers,
+                &self.result,
+                &self.tiles,
+            ),
+            spectrum,
+            shown,
+            output,
+        });
+    }
+}
@@ -0,0 +1,287 @@
+//! Rendering without a window or surface, for scripts:
+//!
+//! ```text
+//! graphics-toolbox render --image clue.png --shader reveal.wgsl -o out.png
+//! ```
+//!
+//! Runs the same pipeline as the viewer's export, at the image's resolution with the identity
+//! view. Outputs ending in `.exr`, `.npy` or `.f32` get the unclamped float values instead of a
+//! PNG. With `--fallback`, or when no GPU adapter is found, a software adapter
+//! (lavapipe/llvmpipe) is used instead.
+use std::{collections::HashMap, error::Error};
+
+use wgpu::RenderPipeline;
+
+use crate::{
+    batch::{self, BatchArgs},
+    create_quad_pipeline_with_constants,
+    data::Data,
+    export,
+    extract::{self, ExtractArgs, LsbArgs, ScanArgs},
+    gpu::Gpu,
+    sweep::{self, SweepArgs},
+    view::View,
+};
+
+const USAGE: &str = "usage:
+    graphics-toolbox render --image <png> --shader <wgsl> -o <png|exr|npy|f32> [--fallback]
+    graphics-toolbox batch --input <glob> --shader <wgsl> -o <template> [--jobs <n>] [--fallback]
+    graphics-toolbox sweep --image <png> --shader <wgsl> -o <template|gif|apng|png> \
+        [--param <override>] [--from <x>] [--to <x>] [--steps <n>] [--sheet] [--columns <n>] \
+        [--delay <ms>] [--fallback]
+    graphics-toolbox extract --image <png> --shader <wgsl> [-o <bin>] [--hex] [--channels <rgba>] \
+        [--bit-order <msb|lsb>] [--scan <rows|columns>] [--word <n>] [--byte-order <big|little>] \
+        [--fallback]
+    graphics-toolbox lsb --image <png> [-o <bin>] [--hex] [--spec <b1,rgb,msb,xy>] [--step <n>] \
+        [--offset <n>]
+    graphics-toolbox scan --image <png> [--top <n>]";
+
+/// Value following a `--flag`
+pub fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for {flag}"))
+}
+
+/// Number following a `--flag`
+pub fn number<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, String> {
+    let value = value(args, flag)?;
+    value
+        .parse()
+        .map_err(|_| format!("{value} isn't a valid number for {flag}"))
+}
+
+#[derive(Debug, Default)]
+struct RenderArgs {
+    image: String,
+    shader: String,
+    output: String,
+    fallback: bool,
+}
+
+impl RenderArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut ret = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--image" | "-i" => ret.image = value(&mut args, &arg)?,
+                "--shader" | "-s" => ret.shader = value(&mut args, &arg)?,
+                "--output" | "-o" => ret.output = value(&mut args, &arg)?,
+                "--fallback" => ret.fallback = true,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if ret.image.is_empty() || ret.shader.is_empty() || ret.output.is_empty() {
+            return Err("--image, --shader and -o are required".to_owned());
+        }
+        Ok(ret)
+    }
+}
+
+/// Device, queue and friends without any window attached
+#[derive(Debug)]
+pub struct Headless {
+    gpu: Gpu,
+}
+
+impl Headless {
+    pub async fn new(fallback: bool) -> Result<Self, Box<dyn Error>> {
+        let gpu = match Gpu::new(wgpu::Instance::default(), None, fallback).await {
+            Some(gpu) => gpu,
+            None if !fallback => {
+                log::warn!("no GPU adapter found, trying a software one");
+                Gpu::new(wgpu::Instance::default(), None, true)
+                    .await
+                    .ok_or("no adapter found, not even a software one")?
+            }
+            None => return Err("no software adapter found".into()),
+        };
+        log::info!("using {:?}", gpu.adapter.get_info());
+        // errors are caught by error scopes below, anything else is just logged
+        gpu.device
+            .on_uncaptured_error(Box::new(|error| log::error!("{error}")));
+        Ok(Self { gpu })
+    }
+    /// Format to render into to save the result as `name`
+    pub fn target_format(&self, name: &str) -> wgpu::TextureFormat {
+        export::target_format(&self.gpu.adapter, name)
+    }
+    /// Compile a user shader for [`Headless::render`], with any errors in the result instead of
+    /// the log. `constants` sets its `override` declarations
+    pub async fn pipeline(
+        &self,
+        name: &str,
+        code: &str,
+        format: wgpu::TextureFormat,
+        constants: &HashMap<String, f64>,
+    ) -> Result<RenderPipeline, Box<dyn Error>> {
+        let device = &self.gpu.device;
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self.gpu.create_shader(name, code);
+        let pipeline = create_quad_pipeline_with_constants(
+            device,
+            &self.gpu.layout,
+            &module,
+            "vs_main",
+            "fs_main",
+            format,
+            None,
+            constants,
+        );
+        match device.pop_error_scope().await {
+            Some(error) => Err(error.into()),
+            None => Ok(pipeline),
+        }
+    }
+    /// Run a compiled shader over an image at its own resolution. Resolves to the pixels of a
+    /// `format` texture, which has to be what the pipeline was made for
+    pub async fn render(
+        &self,
+        pipeline: &RenderPipeline,
+        img: image::DynamicImage,
+        format: wgpu::TextureFormat,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let device = &self.gpu.device;
+        let size = (img.width(), img.height());
+        let img_dim = (size.0 as f32, size.1 as f32);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let texture = self.gpu.create_image_texture(img);
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let viewport =
+            self.gpu
+                .new_viewport(View::default(), String::new(), Some(&texture_view), None);
+        let target = export::render_offscreen(
+            device,
+            &self.gpu.queue,
+            pipeline,
+            &viewport,
+            &Data::identity(img_dim, 0.0),
+            size,
+            format,
+        )
+        .ok_or("image texture missing")?;
+        if let Some(error) = device.pop_error_scope().await {
+            return Err(error.into());
+        }
+        Ok(export::read_texture(device, &self.gpu.queue, &target).await?)
+    }
+}
+
+async fn render(args: RenderArgs) -> Result<(), Box<dyn Error>> {
+    let img = image::open(&args.image)?;
+    let code = std::fs::read_to_string(&args.shader)?;
+    let size = (img.width(), img.height());
+    let headless = Headless::new(args.fallback).await?;
+    let format = headless.target_format(&args.output);
+    let pipeline = headless
+        .pipeline(&args.shader, &code, format, &HashMap::new())
+        .await?;
+    let pixels = headless.render(&pipeline, img, format).await?;
+    for (name, contents) in export::encode(&args.output, format, size, pixels)? {
+        std::fs::write(name, contents)?;
+    }
+    Ok(())
+}
+
+/// Run a command line subcommand, if the first argument names one. `None` means start the
+/// viewer, which is also what any other argument (such as an image path) gets
+pub fn main(mut args: impl Iterator<Item = String>) -> Option<i32> {
+    let result = match args.next()?.as_str() {
+        "render" => RenderArgs::parse(args)
+            .map_err(Box::from)
+            .and_then(|args| pollster::block_on(render(args))),
+        "batch" => BatchArgs::parse(args)
+            .map_err(Box::from)
+            .and_then(batch::run),
+        "sweep" => SweepArgs::parse(args)
+            .map_err(Box::from)
+            .and_then(sweep::run),
+        "extract" => ExtractArgs::parse(args)
+            .map_err(Box::from)
+            .and_then(extract::run),
+        "lsb" => LsbArgs::parse(args)
+            .map_err(Box::from)
+            .and_then(extract::run_lsb),
+        "scan" => ScanArgs::parse(args)
+            .map_err(Box::from)
+            .and_then(extract::run_scan),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            return Some(0);
+        }
+        _ => return None,
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(err) => {
+            eprintln!("error: {err}");
+            Some(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bits::BITS_FORMAT,
+        histogram::{HistogramCounter, Source, BINS},
+    };
+
+    fn run(args: &[&str]) -> Option<i32> {
+        main(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn only_subcommands_leave_the_viewer() {
+        assert_eq!(run(&[]), None);
+        assert_eq!(run(&["clue.png"]), None);
+        assert_eq!(run(&["--help"]), Some(0));
+        // a subcommand with bad arguments still exits rather than opening the viewer
+        assert_eq!(run(&["render"]), Some(1));
+    }
+
+    #[test]
+    fn output_histogram_counts_offscreen_render() {
+        let Ok(headless) = pollster::block_on(Headless::new(true)) else {
+            eprintln!("no adapter, skipping");
+            return;
+        };
+        let gpu = &headless.gpu;
+        let code = concat!(
+            include_str!("shaders/common.wgsl"),
+            "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0, 0.0, 0.5, 1.0); }"
+        );
+        let pipeline =
+            pollster::block_on(headless.pipeline("solid", code, BITS_FORMAT, &HashMap::new()))
+                .unwrap();
+        let img = image::DynamicImage::new_rgba8(8, 4);
+        let texture = gpu.create_image_texture(img);
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let viewport = gpu.new_viewport(View::default(), String::new(), Some(&texture_view), None);
+        let target = export::render_offscreen(
+            &gpu.device,
+            &gpu.queue,
+            &pipeline,
+            &viewport,
+            &Data::identity((8.0, 4.0), 0.0),
+            (8, 4),
+            BITS_FORMAT,
+        )
+        .unwrap();
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let counts = pollster::block_on(HistogramCounter::new(&gpu.device).count(
+            &gpu.device,
+            &gpu.queue,
+            Source::Output(&view),
+            [0, 0, 8, 4],
+        ))
+        .unwrap();
+        assert_eq!(counts[255], 32);
+        assert_eq!(counts[BINS], 32);
+        assert_eq!(counts[2 * BINS + 128], 32);
+        assert_eq!(counts[3 * BINS + 255], 32);
+    }
+}
@@ -0,0 +1,389 @@
+//! Histograms of R, G, B, A and luma, shown in a panel over the bottom of a window. Gaps and
+//! combs in them give away values that were edited or quantized.
+//!
+//! Counting runs in a compute shader where there are compute shaders, WebGL has none so it falls
+//! back to the CPU there.
+//!
+//! I shows the image's histogram and shift+I the shader output's, ctrl+I toggles log scale.
+//! Shift+dragging over the image selects a rectangle to count on its own, alt+I switches between
+//! that (or the visible part of the image, before anything is selected) and the whole image.
+use std::{borrow::Cow, future::Future};
+
+use wgpu::{Device, Queue};
+
+use crate::{export, raw::RawImage, viewport::Rect};
+
+/// Bins per channel, values with more bits are shifted down to fit
+pub const BINS: usize = 256;
+/// R, G, B, A and luma
+pub const CHANNELS: [&str; 5] = ["R", "G", "B", "A", "luma"];
+/// Bin counts, one channel after another
+pub type Counts = Vec<u32>;
+/// Min inclusive and max exclusive corners of the counted area, in image pixels
+pub type Region = [u32; 4];
+
+/// What a histogram counts
+#[derive(Debug, Clone, Copy)]
+pub enum Source<'a> {
+    /// Integer values of the image, with how many bits they have
+    Raw(&'a wgpu::TextureView, u32),
+    /// Shader output rendered to an 8-bit texture
+    Output(&'a wgpu::TextureView),
+}
+
+fn luma(r: u32, g: u32, b: u32) -> u32 {
+    (299 * r + 587 * g + 114 * b + 500) / 1000
+}
+
+/// Pixels of the rectangle with corners at two image positions, clamped to the image
+pub fn region_between(a: (f32, f32), b: (f32, f32), (width, height): (f32, f32)) -> Region {
+    let clamp = |x: f32, max: f32| x.clamp(0.0, max) as u32;
+    [
+        clamp(a.0.min(b.0).floor(), width),
+        clamp(a.1.min(b.1).floor(), height),
+        clamp(a.0.max(b.0).ceil(), width),
+        clamp(a.1.max(b.1).ceil(), height),
+    ]
+}
+
+/// Count on the CPU, for when there are no compute shaders
+pub fn count_cpu(raw: &RawImage, [x0, y0, x1, y1]: Region) -> Counts {
+    let mut counts = vec![0; BINS * CHANNELS.len()];
+    let shift = raw.depth - 8;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let value: Vec<u32> = raw
+                .pixel(x, y)
+                .iter()
+                .map(|&v| (v >> shift) as u32)
+                .collect();
+            for (c, &v) in value.iter().enumerate() {
+                counts[c * BINS + v as usize] += 1;
+            }
+            counts[4 * BINS + luma(value[0], value[1], value[2]) as usize] += 1;
+        }
+    }
+    counts
+}
+
+#[derive(Debug)]
+pub struct HistogramCounter {
+    raw: wgpu::ComputePipeline,
+    output: wgpu::ComputePipeline,
+}
+
+impl HistogramCounter {
+    pub fn new(device: &Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("histogram count"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                "shaders/histogram_count.wgsl"
+            ))),
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("histogram count"),
+                layout: None,
+                module: &shader,
+                entry_point,
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        Self {
+            raw: pipeline("count_raw"),
+            output: pipeline("count_output"),
+        }
+    }
+    pub fn count(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        source: Source,
+        region: Region,
+    ) -> impl 'static + Future<Output = Result<Counts, wgpu::BufferAsyncError>> {
+        let (pipeline, binding, view, shift) = match source {
+            Source::Raw(view, depth) => (&self.raw, 0, view, depth - 8),
+            Source::Output(view) => (&self.output, 1, view, 0),
+        };
+        let size = (BINS * CHANNELS.len() * 4) as u64;
+        let bins = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("histogram"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("histogram readback"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let contents: Vec<u8> = region
+            .into_iter()
+            .chain([shift, 0, 0, 0])
+            .flat_map(u32::to_le_bytes)
+            .collect();
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("histogram params"),
+            size: contents.len() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&params, 0, &contents);
+        let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: bins.as_entire_binding(),
+                },
+            ],
+        });
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        cpass.set_pipeline(pipeline);
+        cpass.set_bind_group(0, &group, &[]);
+        let [x0, y0, x1, y1] = region;
+        cpass.dispatch_workgroups((x1 - x0).div_ceil(16), (y1 - y0).div_ceil(16), 1);
+        drop(cpass);
+        encoder.copy_buffer_to_buffer(&bins, 0, &readback, 0, size);
+        queue.submit(Some(encoder.finish()));
+        let mapped = export::map_buffer(device, readback);
+        async move {
+            let buffer = mapped.await?;
+            let counts = buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks_exact(4)
+                .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+                .collect();
+            buffer.unmap();
+            Ok(counts)
+        }
+    }
+}
+
+/// Per-window panel state, and the texture its bars are drawn from
+#[derive(Debug)]
+pub struct Histogram {
+    pipeline: wgpu::RenderPipeline,
+    heights: wgpu::Texture,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// Last counts, the panel is hidden while there are none
+    pub counts: Option<Counts>,
+    /// Count the focused viewport's shader output instead of the image
+    pub output: bool,
+    pub log: bool,
+    /// Only count `selection`, or the part of the image the focused viewport shows without one
+    pub region: bool,
+    /// Rectangle picked by shift+dragging
+    pub selection: Option<Region>,
+    /// Image position a shift+drag started at, while it's going on
+    pub selecting: Option<(f32, f32)>,
+    pub channels: [bool; 5],
+}
+
+impl Histogram {
+    pub fn new(device: &Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("histogram"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/histogram.wgsl"))),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("histogram"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let heights = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("histogram"),
+            size: wgpu::Extent3d {
+                width: BINS as u32,
+                height: CHANNELS.len() as u32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // filterable, which the layout wgpu makes up for the shader expects
+            format: wgpu::TextureFormat::R16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("histogram"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &heights.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        Self {
+            pipeline,
+            heights,
+            params_buffer,
+            bind_group,
+            counts: None,
+            output: false,
+            log: false,
+            region: false,
+            selection: None,
+            selecting: None,
+            // alpha is mostly one bar at 255 that dwarfs everything else
+            channels: [true, true, true, false, true],
+        }
+    }
+    /// Show new counts
+    pub fn set_counts(&mut self, queue: &Queue, counts: Counts) {
+        self.counts = Some(counts);
+        self.upload(queue);
+    }
+    /// Scale the bars to the panel, after the counts, the scale or the shown channels changed.
+    /// Shown channels share the tallest bar so they can be compared
+    pub fn upload(&self, queue: &Queue) {
+        let Some(counts) = &self.counts else {
+            return;
+        };
+        let scale = |count: u32| match self.log {
+            true => (count as f32).ln_1p(),
+            false => count as f32,
+        };
+        let max = counts
+            .chunks(BINS)
+            .zip(self.channels)
+            .filter(|&(_, shown)| shown)
+            .flat_map(|(bins, _)| bins.iter().copied())
+            .max()
+            .unwrap_or(0);
+        let max = scale(max).max(1.0);
+        let heights: Vec<u8> = counts
+            .iter()
+            .flat_map(|&count| half::f16::from_f32(scale(count) / max).to_ne_bytes())
+            .collect();
+        queue.write_texture(
+            self.heights.as_image_copy(),
+            &heights,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(2 * BINS as u32),
+                rows_per_image: None,
+            },
+            self.heights.size(),
+        );
+    }
+    /// Where the panel goes in a window, along the bottom
+    pub fn panel(width: u32, height: u32) -> Rect {
+        let panel_height = (height / 3).max(1);
+        Rect {
+            x: 0,
+            y: height - panel_height,
+            width: width.max(1),
+            height: panel_height,
+        }
+    }
+    /// Bin under a window position, if the panel is showing and the position is over it
+    pub fn bin_at(&self, (x, y): (f32, f32), width: u32, height: u32) -> Option<usize> {
+        let panel = Self::panel(width, height);
+        (self.counts.is_some() && panel.contains((x, y)))
+            .then(|| ((x / panel.width as f32 * BINS as f32) as usize).min(BINS - 1))
+    }
+    /// Counts of every shown channel in a bin
+    pub fn readout(&self, bin: usize) -> String {
+        let Some(counts) = &self.counts else {
+            return String::new();
+        };
+        let channels: Vec<String> = CHANNELS
+            .iter()
+            .enumerate()
+            .filter(|&(c, _)| self.channels[c])
+            .map(|(c, name)| format!("{name} {}", counts[c * BINS + bin]))
+            .collect();
+        format!("bin {bin}: {}", channels.join(", "))
+    }
+    /// Draw over whatever is in the pass, with `cursor_bin` highlighted
+    pub fn draw(&self, queue: &Queue, rpass: &mut wgpu::RenderPass, cursor_bin: Option<usize>) {
+        let channels = (0..CHANNELS.len())
+            .filter(|&c| self.channels[c])
+            .fold(0u32, |mask, c| mask | 1 << c);
+        let cursor_bin = cursor_bin.map_or(-1, |bin| bin as i32);
+        let mut params = [0u8; 16];
+        params[..4].copy_from_slice(&channels.to_le_bytes());
+        params[4..8].copy_from_slice(&cursor_bin.to_le_bytes());
+        queue.write_buffer(&self.params_buffer, 0, &params);
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_between_covers_touched_pixels() {
+        let img_dim = (400.0, 300.0);
+        assert_eq!(
+            region_between((10.5, 20.2), (30.5, 40.7), img_dim),
+            [10, 20, 31, 41]
+        );
+        // dragging up and left selects the same rectangle
+        assert_eq!(
+            region_between((30.5, 40.7), (10.5, 20.2), img_dim),
+            [10, 20, 31, 41]
+        );
+        // past the edges is clamped to the image
+        assert_eq!(
+            region_between((-50.0, 250.0), (500.0, 350.0), img_dim),
+            [0, 250, 400, 300]
+        );
+    }
+}
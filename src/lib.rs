@@ -1,309 +1,1879 @@
 #![allow(clippy::single_match)]
+use bitplane::Plane;
 use core::str;
+use data::Data;
+use export::FloatFormat;
+use gpu::Gpu;
+use histogram::Source;
 use image::GenericImageView;
-use std::{borrow::Cow, future::Future};
-use wgpu::{
-    Adapter, BindGroup, BindGroupLayout, BufferUsages, Device, Queue, RenderPipeline, Surface,
-    SurfaceConfiguration,
+use split::WipeShape;
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
 };
+use view::View;
+use viewport::Viewport;
+use wgpu::{BindGroupLayout, Device, RenderPipeline};
+use window::AppWindow;
 use winit::{
-    application::ApplicationHandler, event::WindowEvent, event_loop::EventLoop, window::Window,
+    application::ApplicationHandler, event::WindowEvent, event_loop::EventLoop, window::WindowId,
 };
 use winit_proxy::WinitProxy;
 
 use platform::{Platform, PlatformTrait};
+use raw::RawImage;
+use spectrum::Spectrum;
+use stereo::Stereo;
 
+mod audio;
+mod background;
+#[cfg(not(target_arch = "wasm32"))]
+mod batch;
+mod bitplane;
+mod bits;
+mod colors;
+mod compare;
+mod data;
+mod ela;
+mod export;
+#[cfg(not(target_arch = "wasm32"))]
+mod extract;
+mod filter;
+mod gpu;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod histogram;
+mod lsb;
+mod mipmap;
 mod platform;
+mod raw;
+mod session;
+mod spectrum;
+mod split;
+mod steg;
+mod stereo;
+#[cfg(not(target_arch = "wasm32"))]
+mod sweep;
+mod tiles;
+mod view;
+mod viewport;
+mod window;
 mod winit_proxy;
 
+const IMAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const TITLE: &str = "Neuro ARG Toolbox Ultimate Pro Deluxe";
+// the rest of a scan's hits are usually noise
+const SCAN_HITS_SHOWN: usize = 20;
+
 #[derive(Debug)]
 struct App {
-    config: SurfaceConfiguration,
-    surface: Surface<'static>,
-    device: Device,
-    adapter: Adapter,
-    window: &'static Window,
-    render_pipeline: Option<RenderPipeline>,
-    queue: Queue,
-    layout: BindGroupLayout,
+    gpu: Gpu,
     // platform-specific code
-    _platform: Platform,
+    platform: Platform,
+    windows: HashMap<WindowId, AppWindow>,
+    // every window is assumed to use the same swapchain format as the first one
+    format: wgpu::TextureFormat,
     // stuff to load/reload later
-    bind_group: Option<BindGroup>,
-    data_buffer: wgpu::Buffer,
+    // user shaders by file name, shared by all viewports drawing with them
+    modules: HashMap<String, wgpu::ShaderModule>,
+    // kept for session bundles
+    sources: HashMap<String, String>,
+    // sha256 of loaded image files, also for bundles
+    image_hashes: BTreeMap<String, String>,
+    pipelines: HashMap<String, RenderPipeline>,
+    texture: Option<wgpu::Texture>,
+    texture_view: Option<wgpu::TextureView>,
+    // integer copy of the image for the bit-plane viewer, since the sRGB texture isn't bit exact
+    raw_view: Option<wgpu::TextureView>,
+    // exact channel values, for bit planes and LSB extraction
+    raw: Option<Arc<RawImage>>,
     img_dim: (f32, f32),
-    pos: (f32, f32),
-    scale: f32,
+    modifiers: winit::keyboard::ModifiersState,
+    // when off, only the base level is bound so zoomed out views alias like they used to
+    mipmaps: bool,
+    lod_bias: f32,
+    // what ctrl+shift+S saves shader output as
+    float_format: FloatFormat,
+    // how ctrl+B turns shader output into bytes, set by editing bits::PACKING_FILE
+    packing: bits::Packing,
+    // what session::NOTES_FILE says, saved with bundles
+    notes: String,
+    // what ctrl+L reads from the image, set by editing lsb::SPEC_FILE
+    lsb: lsb::Spec,
+    // distinct colors of the image most common first, counted the first time they're asked for
+    colors: Option<Vec<(colors::Color, u32)>>,
+    // the image's Fourier transform, also only done once asked for
+    spectrum: Option<Arc<Spectrum>>,
+    // what the frequency filter keeps, set by editing filter::FILTER_FILE
+    filter: filter::Spec,
+    // notches painted onto spectrum views
+    painted: Vec<filter::Shape>,
+    // red channel of the shader `filter` names, if it has rendered
+    shader_mask: Option<Vec<f32>>,
+    // centred product of all of the above, `None` when it would keep everything
+    mask: Option<Vec<f32>>,
+    // whether there is a filter result to keep up to date
+    filtered: bool,
+    // how the JPEG error level analysis image is made
+    ela: ela::Settings,
+    // only made while some viewport uses it
+    ela_texture: Option<wgpu::Texture>,
+    ela_view: Option<wgpu::TextureView>,
+    // slot B of the comparison, from compare::SLOT_B_FILE
+    compare_raw: Option<Arc<RawImage>>,
+    compare_mode: compare::Mode,
+    // where slot B's top left corner goes in the image
+    compare_offset: (i32, i32),
+    // only made while some viewport compares and there is a slot B
+    comparison: Option<(Arc<compare::Summary>, wgpu::Texture)>,
+    compare_view: Option<wgpu::TextureView>,
+    // where each tile of a scrambled image goes, set by dragging in a tile view or editing
+    // tiles::TILES_FILE
+    tiles: tiles::Arrangement,
+    // whether there is a put-together image to keep up to date
+    tiled: bool,
+    // last audio file loaded, its spectrogram is the image until another image loads
+    audio: Option<audio::Audio>,
+    // how the spectrogram is made, set by editing audio::SETTINGS_FILE
+    spectrogram: audio::Settings,
 }
 
 impl App {
-    // must be a multiple of 16 bytes
-    fn buf_contents(&self) -> [u8; 32] {
-        let mut ret = [0u8; 32];
-        let win_dim = self.window.inner_size();
-        for (dst, src) in ret.chunks_exact_mut(4).zip([
-            self.img_dim.0,
-            self.img_dim.1,
-            win_dim.width as f32,
-            win_dim.height as f32,
-            self.pos.0,
-            self.pos.1,
-            self.scale,
-        ]) {
-            dst.copy_from_slice(&src.to_le_bytes());
-        }
-        ret
-    }
-    fn load_shader(&mut self, shader: &str) {
-        let shader = self
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader)),
-            });
-        let pipeline_layout = self
-            .device
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: &[&self.layout],
-                push_constant_ranges: &[],
-            });
-
-        let swapchain_capabilities = self.surface.get_capabilities(&self.adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
-
-        let render_pipeline = self
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[],
-                    compilation_options: Default::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(swapchain_format.into())],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleStrip,
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            });
-        self.render_pipeline = Some(render_pipeline);
+    fn load_shader(&mut self, name: &str, code: &str) {
+        let shader = self.gpu.create_shader(name, code);
+        let render_pipeline = create_quad_pipeline(
+            &self.gpu.device,
+            &self.gpu.layout,
+            &shader,
+            "vs_main",
+            "fs_main",
+            self.format,
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+        self.modules.insert(name.to_owned(), shader);
+        self.sources.insert(name.to_owned(), code.to_owned());
+        self.pipelines.insert(name.to_owned(), render_pipeline);
     }
     fn load_image(&mut self, img: image::DynamicImage) {
         let dimensions = img.dimensions();
-        let rgba = img.into_rgba8();
         self.img_dim = (dimensions.0 as f32, dimensions.1 as f32);
-
-        let size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
-        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
-        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            label: None,
+        let raw = RawImage::new(&img);
+        let raw_texture = bitplane::raw_texture(&self.gpu.device, &self.gpu.queue, &raw);
+        self.raw_view = Some(raw_texture.create_view(&Default::default()));
+        self.raw = Some(Arc::new(raw));
+        self.colors = None;
+        self.spectrum = None;
+        self.texture = Some(self.gpu.create_image_texture(img));
+        self.ela_texture = self.create_ela_texture();
+        self.comparison = self.create_comparison();
+        self.update_bind_groups();
+        let spectra: Vec<_> = self
+            .windows
+            .iter()
+            .flat_map(|(&id, window)| {
+                window
+                    .viewports
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(i, viewport)| {
+                        Some((id, i, viewport.spectrum.as_ref()?.output))
+                    })
+            })
+            .collect();
+        for (id, i, output) in spectra {
+            self.refresh_spectrum(id, i, output);
+        }
+        self.refresh_shader_mask();
+        self.update_filter();
+        if self.tiled {
+            self.apply_tiles();
+        }
+        let shown: Vec<_> = self
+            .windows
+            .iter()
+            .filter(|(_, window)| window.histogram.counts.is_some())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in shown {
+            self.refresh_histogram(id);
+        }
+    }
+    fn update_bind_groups(&mut self) {
+        self.texture_view = self.texture.as_ref().map(|texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                mip_level_count: (!self.mipmaps).then_some(1),
+                ..Default::default()
+            })
+        });
+        self.ela_view = self.ela_texture.as_ref().map(|texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                mip_level_count: (!self.mipmaps).then_some(1),
+                ..Default::default()
+            })
+        });
+        self.compare_view = self.comparison.as_ref().map(|(_, texture)| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                mip_level_count: (!self.mipmaps).then_some(1),
+                ..Default::default()
+            })
+        });
+        let bit_depth = self.bit_depth();
+        for window in self.windows.values_mut() {
+            window.isolation.update_bind_group(
+                &self.gpu.device,
+                &self.gpu.plane_layout,
+                self.raw_view.as_ref(),
+            );
+        }
+        for viewport in self
+            .windows
+            .values_mut()
+            .flat_map(|window| &mut window.viewports)
+        {
+            viewport.update_bind_group(
+                &self.gpu.device,
+                &self.gpu.layout,
+                self.texture_view.as_ref(),
+                &self.gpu.samplers,
+                &self.gpu.result,
+                &self.gpu.tiles,
+            );
+            viewport.ela_group = self.ela_view.as_ref().map(|view| {
+                viewport.create_bind_group(
+                    &self.gpu.device,
+                    &self.gpu.layout,
+                    view,
+                    &self.gpu.samplers,
+                    &self.gpu.result,
+                    &self.gpu.tiles,
+                )
+            });
+            viewport.update_compare_layer(
+                &self.gpu.device,
+                &self.gpu.layout,
+                self.comparison
+                    .as_ref()
+                    .map(|(summary, _)| summary)
+                    .zip(self.compare_view.as_ref()),
+                &self.gpu.samplers,
+                &self.gpu.result,
+                &self.gpu.tiles,
+            );
+            viewport.update_plane_bind_group(
+                &self.gpu.device,
+                &self.gpu.plane_layout,
+                self.raw_view.as_ref(),
+            );
+            if let Some(plane) = &mut viewport.plane {
+                plane.set_depth(bit_depth);
+            }
+        }
+    }
+    fn bit_depth(&self) -> u32 {
+        self.raw.as_ref().map_or(8, |raw| raw.depth)
+    }
+    fn new_viewport(&self, view: View, shader: String) -> Viewport {
+        let mut viewport = self.gpu.new_viewport(
+            view,
+            shader,
+            self.texture_view.as_ref(),
+            self.raw_view.as_ref(),
+        );
+        viewport.ela_group = self.ela_view.as_ref().map(|view| {
+            viewport.create_bind_group(
+                &self.gpu.device,
+                &self.gpu.layout,
+                view,
+                &self.gpu.samplers,
+                &self.gpu.result,
+                &self.gpu.tiles,
+            )
+        });
+        viewport
+    }
+    /// The image compared with slot B, if there is one and any viewport shows the comparison
+    fn create_comparison(&self) -> Option<(Arc<compare::Summary>, wgpu::Texture)> {
+        let (a, b) = (self.raw.as_ref()?, self.compare_raw.as_ref()?);
+        let in_use = self
+            .windows
+            .values()
+            .flat_map(|window| &window.viewports)
+            .any(|viewport| viewport.compare);
+        if !in_use {
+            return None;
+        }
+        let (raw, summary) = compare::compare(a, b, self.compare_mode, self.compare_offset);
+        println!("{summary}");
+        let texture = self.gpu.create_image_texture(raw.to_image());
+        Some((Arc::new(summary), texture))
+    }
+    /// After slot B, the mode or the offset change or a viewport starts or stops comparing
+    fn update_comparison(&mut self) {
+        self.comparison = self.create_comparison();
+        self.update_bind_groups();
+        for window in self.windows.values_mut() {
+            window.update_title(self.img_dim);
+        }
+        self.request_redraw_all();
+    }
+    /// Error level analysis of the image with the current settings, if any viewport shows it
+    fn create_ela_texture(&mut self) -> Option<wgpu::Texture> {
+        let raw = self.raw.clone()?;
+        let in_use = self
+            .windows
+            .values()
+            .flat_map(|window| &window.viewports)
+            .any(|viewport| viewport.ela != ela::Mode::Off);
+        if !in_use {
+            return None;
+        }
+        match ela::ela(&raw, self.ela) {
+            Ok(img) => Some(self.gpu.create_image_texture(img)),
+            Err(err) => {
+                self.platform.error_reporter()(Box::new(err));
+                None
+            }
+        }
+    }
+    /// After the ELA settings change or a viewport starts or stops showing it
+    fn update_ela(&mut self) {
+        self.ela_texture = self.create_ela_texture();
+        self.update_bind_groups();
+        self.request_redraw_all();
+    }
+    /// Copy of the focused viewport of a window, to open next to it or in a new window
+    fn clone_viewport(&self, window_id: WindowId) -> Option<Viewport> {
+        let window = self.windows.get(&window_id)?;
+        let viewport = &window.viewports[window.focused];
+        let mut ret = self.new_viewport(viewport.view.clone(), viewport.shader.clone());
+        ret.plane = viewport.plane;
+        ret.result = viewport.result;
+        ret.ela = viewport.ela;
+        ret.compare = viewport.compare;
+        ret.stereo = viewport.stereo;
+        ret.tiles = viewport.tiles;
+        ret.update_compare_layer(
+            &self.gpu.device,
+            &self.gpu.layout,
+            self.comparison
+                .as_ref()
+                .map(|(summary, _)| summary)
+                .zip(self.compare_view.as_ref()),
+            &self.gpu.samplers,
+            &self.gpu.result,
+            &self.gpu.tiles,
+        );
+        if let Some(layer) = &viewport.spectrum {
+            let mask = self.mask.as_deref().filter(|_| !layer.output);
+            self.gpu.show_spectrum(
+                &mut ret,
+                layer.spectrum.clone(),
+                layer.shown,
+                layer.output,
+                mask,
+            );
+        }
+        Some(ret)
+    }
+    fn open_window(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        viewport: Viewport,
+    ) -> WindowId {
+        let window = Arc::new(
+            event_loop
+                .create_window(Platform::set_extra_window_attrs(
+                    winit::window::WindowAttributes::default().with_title(TITLE),
+                ))
+                .unwrap(),
+        );
+        let surface = self.gpu.instance.create_surface(window.clone()).unwrap();
+        let mut window = AppWindow::new(
+            &self.gpu.device,
+            &self.gpu.adapter,
+            &self.gpu.layout,
+            &self.gpu.plane_layout,
+            window,
+            surface,
+            viewport,
+        );
+        window.isolation.update_bind_group(
+            &self.gpu.device,
+            &self.gpu.plane_layout,
+            self.raw_view.as_ref(),
+        );
+        window.window.request_redraw();
+        let id = window.window.id();
+        self.windows.insert(id, window);
+        id
+    }
+    fn focused_shader(&self, window_id: WindowId) -> Option<&str> {
+        let window = self.windows.get(&window_id)?;
+        Some(&window.viewports[window.focused].shader)
+    }
+    /// Render the focused viewport's shader at the image's resolution, ignoring its view.
+    fn render_focused(
+        &self,
+        window_id: WindowId,
+        format: wgpu::TextureFormat,
+    ) -> Option<wgpu::Texture> {
+        self.render_shader(self.focused_shader(window_id)?, format)
+    }
+    /// Render a shader at the image's resolution
+    fn render_shader(&self, shader: &str, format: wgpu::TextureFormat) -> Option<wgpu::Texture> {
+        let module = self.modules.get(shader)?;
+        let pipeline = create_quad_pipeline(
+            &self.gpu.device,
+            &self.gpu.layout,
+            module,
+            "vs_main",
+            "fs_main",
+            format,
+            None,
+        );
+        let viewport = self.new_viewport(View::default(), shader.to_owned());
+        let size = (self.img_dim.0 as u32, self.img_dim.1 as u32);
+        export::render_offscreen(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &pipeline,
+            &viewport,
+            &Data::identity(self.img_dim, self.lod_bias),
             size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
             format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
+        )
+    }
+    /// Save the focused viewport's shader output as a PNG, or as floats in `float_format` if
+    /// `float`
+    fn export(&mut self, window_id: WindowId, float: bool) {
+        let Some(stem) = self.focused_shader(window_id) else {
+            return;
+        };
+        let stem = stem.trim_end_matches(".wgsl");
+        let name = match float {
+            true => format!("{stem}-export.{}", self.float_format.extension()),
+            false => format!("{stem}-export.png"),
+        };
+        let format = export::target_format(&self.gpu.adapter, &name);
+        let Some(texture) = self.render_focused(window_id, format) else {
+            return;
+        };
+        let size = (texture.width(), texture.height());
+        let pixels = export::read_texture(&self.gpu.device, &self.gpu.queue, &texture);
+        let reporter = self.platform.error_reporter();
+        Platform::run_future(async move {
+            let files = match pixels.await {
+                Ok(pixels) => export::encode(&name, format, size, pixels),
+                Err(err) => Err(Box::from(err)),
+            };
+            let result = files.and_then(|files| {
+                files
+                    .iter()
+                    .try_for_each(|(name, contents)| Platform::save_file(name, contents))
+            });
+            match result {
+                Ok(()) => println!("exported {name}"),
+                Err(err) => reporter(err),
+            }
         });
-
-        self.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                aspect: wgpu::TextureAspect::All,
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            &rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * dimensions.0),
-                rows_per_image: Some(dimensions.1),
-            },
-            size,
+    }
+    /// Pack the bits the focused viewport's shader marks into bytes, print the start of them and
+    /// save them all
+    fn extract_bits(&mut self, window_id: WindowId) {
+        let Some(shader) = self.focused_shader(window_id) else {
+            return;
+        };
+        let name = format!("{}-bits.bin", shader.trim_end_matches(".wgsl"));
+        let Some(texture) = self.render_focused(window_id, bits::BITS_FORMAT) else {
+            return;
+        };
+        let size = (texture.width(), texture.height());
+        let pixels = export::read_texture(&self.gpu.device, &self.gpu.queue, &texture);
+        let packing = self.packing.clone();
+        let reporter = self.platform.error_reporter();
+        Platform::run_future(async move {
+            let pixels = match pixels.await {
+                Ok(pixels) => pixels,
+                Err(err) => return reporter(Box::new(err)),
+            };
+            let (bytes, count) = bits::extract(&pixels, size, &packing);
+            print!("{packing}\n{count} bits\n{}", bits::hexdump(&bytes, 256));
+            match Platform::save_file(&name, &bytes) {
+                Ok(()) => println!("saved {name}"),
+                Err(err) => reporter(err),
+            }
+        });
+    }
+    /// Print the first bytes that `lsb` reads from the image
+    fn preview_lsb(&self) {
+        let Some(raw) = &self.raw else {
+            return;
+        };
+        println!(
+            "{}\n{}",
+            self.lsb,
+            lsb::preview(&lsb::extract(raw, &self.lsb, 256), 256)
         );
-
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler1 = self.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
+    }
+    /// Save everything `lsb` reads from the image, named after the file type it looks like
+    fn save_lsb(&mut self) {
+        let Some(raw) = &self.raw else {
+            return;
+        };
+        let bytes = lsb::extract(raw, &self.lsb, usize::MAX);
+        print!("{}\n{}", self.lsb, lsb::preview(&bytes, 256));
+        let extension = lsb::sniff(&bytes).unwrap_or("bin");
+        let name = format!("payload-{}.{extension}", Platform::timestamp());
+        match Platform::save_file(&name, &bytes) {
+            Ok(()) => println!("saved {name}"),
+            Err(err) => self.platform.error_reporter()(err),
+        }
+    }
+    /// Distinct colors of the image, counting them if this is the first time since it loaded
+    fn colors(&mut self) -> Option<&[(colors::Color, u32)]> {
+        let raw = self.raw.as_ref()?;
+        Some(
+            self.colors
+                .get_or_insert_with(|| colors::unique_colors(raw)),
+        )
+    }
+    /// Print how many distinct colors the image has and the most common ones
+    fn list_colors(&mut self) {
+        const SHOWN: usize = 64;
+        let depth = self.bit_depth();
+        let Some(colors) = self.colors() else {
+            return;
+        };
+        println!("{} colors", colors.len());
+        for (i, &(color, count)) in colors.iter().take(SHOWN).enumerate() {
+            println!("{i:>4} {} {count}", colors::hex(color, depth));
+        }
+        if colors.len() > SHOWN {
+            println!("     ...");
+        }
+    }
+    /// Highlight the pixels of one color in a window, keeping its tolerance
+    fn isolate(&mut self, window_id: WindowId, color: colors::Color) {
+        let depth = self.bit_depth();
+        let (Some(window), Some(raw)) = (self.windows.get_mut(&window_id), &self.raw) else {
+            return;
+        };
+        let tolerance = window.isolate.map_or(0, |isolate| isolate.tolerance);
+        let isolate = colors::Isolate { color, tolerance };
+        println!(
+            "isolating {} ±{tolerance}, {} pixels",
+            colors::hex(color, depth),
+            isolate.count(raw)
+        );
+        window.isolate = Some(isolate);
+        window.window.request_redraw();
+    }
+    /// Isolate the color under the cursor
+    fn pick_color(&mut self, window_id: WindowId) {
+        let (Some(window), Some(raw)) = (self.windows.get(&window_id), &self.raw) else {
+            return;
+        };
+        if window.viewports[window.focused]
+            .plane
+            .is_some_and(|plane| plane.grid)
+        {
+            return;
+        }
+        let Some((x, y)) = window.cursor_pixel(self.img_dim) else {
+            return;
+        };
+        if x < 0.0 || y < 0.0 || x >= self.img_dim.0 || y >= self.img_dim.1 {
+            return;
+        }
+        let color = raw.pixel(x as u32, y as u32).try_into().unwrap();
+        self.isolate(window_id, color);
+    }
+    /// Isolate the next or previous color in order of how common it is
+    fn step_color(&mut self, window_id: WindowId, step: isize) {
+        let current = self
+            .windows
+            .get(&window_id)
+            .and_then(|window| window.isolate)
+            .map(|isolate| isolate.color);
+        let Some(colors) = self.colors() else {
+            return;
+        };
+        let i = current
+            .and_then(|color| colors.iter().position(|&(c, _)| c == color))
+            .map_or(0, |i| {
+                (i as isize + step).rem_euclid(colors.len() as isize) as usize
+            });
+        let color = colors[i].0;
+        print!("#{i} ");
+        self.isolate(window_id, color);
+    }
+    /// Show the spectrum of the image or of the shader output in the focused viewport, or go
+    /// back to the shader if that is what it already shows
+    fn toggle_spectrum(&mut self, window_id: WindowId, output: bool) {
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+        let focused = window.focused;
+        let viewport = window.focused_mut();
+        if viewport
+            .spectrum
+            .as_ref()
+            .is_some_and(|layer| layer.output == output)
+        {
+            viewport.spectrum = None;
+            window.update_title(self.img_dim);
+            return window.window.request_redraw();
+        }
+        self.refresh_spectrum(window_id, focused, output);
+    }
+    /// Transform again after the image or shader changed. The image's spectrum is shared and
+    /// shown right away, shader output comes back as `Event::Spectrum`
+    fn refresh_spectrum(&mut self, window_id: WindowId, index: usize, output: bool) {
+        let Some(raw) = &self.raw else {
+            return;
+        };
+        if !output {
+            let spectrum = self
+                .spectrum
+                .get_or_insert_with(|| Arc::new(Spectrum::new(raw)))
+                .clone();
+            return self.show_spectrum(window_id, index, spectrum, false);
+        }
+        let Some(shader) = self
+            .windows
+            .get(&window_id)
+            .and_then(|window| window.viewports.get(index))
+            .map(|viewport| viewport.shader.clone())
+        else {
+            return;
+        };
+        let Some(texture) = self.render_shader(&shader, bits::BITS_FORMAT) else {
+            return;
+        };
+        let (width, height) = (texture.width(), texture.height());
+        let pixels = export::read_texture(&self.gpu.device, &self.gpu.queue, &texture);
+        let sender = self.platform.event_sender();
+        let reporter = self.platform.error_reporter();
+        Platform::run_future(async move {
+            match pixels.await {
+                Ok(pixels) => {
+                    let output = RawImage {
+                        width,
+                        height,
+                        depth: 8,
+                        values: pixels.into_iter().map(u16::from).collect(),
+                    };
+                    let spectrum = Arc::new(Spectrum::new(&output));
+                    sender.send_event(Event::Spectrum(window_id, index, spectrum));
+                }
+                Err(err) => reporter(Box::new(err)),
+            }
         });
-        let sampler2 = self.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
+    }
+    /// Put a spectrum in a viewport, keeping what part of it was shown before
+    fn show_spectrum(
+        &mut self,
+        window_id: WindowId,
+        index: usize,
+        spectrum: Arc<Spectrum>,
+        output: bool,
+    ) {
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+        let Some(viewport) = window.viewports.get_mut(index) else {
+            return;
+        };
+        let shown = viewport
+            .spectrum
+            .as_ref()
+            .map_or_else(Default::default, |layer| layer.shown);
+        let mask = self.mask.as_deref().filter(|_| !output);
+        self.gpu
+            .show_spectrum(viewport, spectrum, shown, output, mask);
+        window.update_title(self.img_dim);
+        window.window.request_redraw();
+    }
+    /// Work out the filter mask again after any part of it changed and filter again if there is
+    /// a result already
+    fn update_filter(&mut self) {
+        self.update_mask();
+        if self.filtered {
+            self.apply_filter();
+        }
+    }
+    /// Just the mask and how the image's spectra show it, cheap enough to do while painting
+    fn update_mask(&mut self) {
+        let nothing = self.filter.shapes.is_empty()
+            && self.painted.is_empty()
+            && (self.filter.shader.is_none() || self.shader_mask.is_none());
+        self.mask = match (&self.raw, nothing) {
+            (Some(raw), false) => Some(filter::mask(
+                &self.filter,
+                &self.painted,
+                self.shader_mask.as_deref(),
+                (raw.width, raw.height),
+            )),
+            _ => None,
+        };
+        for window in self.windows.values_mut() {
+            for viewport in &mut window.viewports {
+                let Some(layer) = viewport.spectrum.as_ref().filter(|layer| !layer.output) else {
+                    continue;
+                };
+                let (spectrum, shown) = (layer.spectrum.clone(), layer.shown);
+                self.gpu
+                    .show_spectrum(viewport, spectrum, shown, false, self.mask.as_deref());
+            }
+            window.window.request_redraw();
+        }
+    }
+    /// Filter the image with the current mask and bind the result for every viewport
+    fn apply_filter(&mut self) {
+        let Some(raw) = &self.raw else {
+            return;
+        };
+        let spectrum = self
+            .spectrum
+            .get_or_insert_with(|| Arc::new(Spectrum::new(raw)))
+            .clone();
+        let pixels = match &self.mask {
+            Some(mask) => filter::apply(&spectrum, mask),
+            None => filter::apply(&spectrum, &vec![1.0; raw.values.len() / 4]),
+        };
+        let texture = spectrum::float_texture(
+            &self.gpu.device,
+            &self.gpu.queue,
+            "result",
+            (raw.width, raw.height),
+            &pixels,
+        );
+        self.gpu.result = texture.create_view(&Default::default());
+        self.filtered = true;
+        self.update_bind_groups();
+        self.request_redraw_all();
+    }
+    /// Load the spectrogram of the last audio file as the image, with the current settings
+    fn load_spectrogram(&mut self) {
+        let Some(audio) = &self.audio else {
+            return println!(
+                "no audio loaded, save it as one of {:?}",
+                audio::AUDIO_FILES
+            );
+        };
+        let settings = self.spectrogram;
+        let max_dim = self.gpu.device.limits().max_texture_dimension_2d;
+        if settings.window / 2 + 1 > max_dim as usize {
+            return self.platform.error_reporter()(
+                format!("a window of {} is too tall for a texture", settings.window).into(),
+            );
+        }
+        let (img, hop) = audio::spectrogram(audio, &settings, max_dim);
+        let rate = audio.sample_rate as f32;
+        println!("{settings}");
+        if hop != settings.hop {
+            println!("hop raised to {hop} to fit the texture size limit");
+        }
+        println!(
+            "{}×{} spectrogram of {:.2} s, {:.2} ms per column, {}",
+            img.width(),
+            img.height(),
+            audio.samples.len() as f32 / rate,
+            hop as f32 / rate * 1000.0,
+            match settings.log {
+                true => format!(
+                    "{:.1} Hz to {} Hz",
+                    rate / settings.window as f32,
+                    rate / 2.0
+                ),
+                false => format!("{:.2} Hz per row", rate / settings.window as f32),
+            }
+        );
+        self.load_image(img);
+        self.request_redraw_all();
+    }
+    /// Put the image back together from its tiles for binding 5, and show the tile views where
+    /// everything went
+    fn apply_tiles(&mut self) {
+        self.tiled = true;
+        let Some(raw) = self.raw.clone() else {
+            return;
+        };
+        let dim = (raw.width, raw.height);
+        if let Err(err) = self.tiles.fit(dim) {
+            self.platform.error_reporter()(err.into());
+            self.tiles.reset(dim);
+        }
+        let texture = self
+            .gpu
+            .create_image_texture(self.tiles.apply(&raw).to_image());
+        self.gpu.tiles = texture.create_view(&Default::default());
+        self.update_tile_viewers();
+        self.update_bind_groups();
+        self.request_redraw_all();
+    }
+    fn update_tile_viewers(&mut self) {
+        let dim = (self.img_dim.0 as u32, self.img_dim.1 as u32);
+        for window in self.windows.values_mut() {
+            window.tile_viewer.update(
+                &self.gpu.device,
+                &self.gpu.queue,
+                &self.gpu.plane_layout,
+                &self.tiles,
+                dim,
+                window.tile_drag,
+            );
+            window.update_title(self.img_dim);
+        }
+    }
+    /// Grid position under the cursor in a window's focused viewport
+    fn tile_under_cursor(&self, window_id: WindowId) -> Option<usize> {
+        let window = self.windows.get(&window_id)?;
+        let dim = (self.img_dim.0 as u32, self.img_dim.1 as u32);
+        let position = self
+            .tiles
+            .position(dim, window.cursor_pixel(self.img_dim)?)?;
+        (position < self.tiles.tiles.len()).then_some(position)
+    }
+    /// Left button in a tile view: remember the tile it went down on, and swap that with the one
+    /// it comes up on
+    fn drag_tile(&mut self, window_id: WindowId, pressed: bool) {
+        let position = self.tile_under_cursor(window_id);
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+        match (pressed, window.tile_drag.take()) {
+            (true, _) => {
+                window.tile_drag = position;
+                self.update_tile_viewers();
+                self.request_redraw_all();
+            }
+            (false, Some(from)) => {
+                if let Some(to) = position.filter(|&to| to != from) {
+                    self.tiles.tiles.swap(from, to);
+                    println!("swapped positions {from} and {to}");
+                }
+                self.apply_tiles();
+            }
+            (false, None) => {}
+        }
+    }
+    /// Turn the tile under the cursor clockwise, or mirror it left to right as it looks now
+    fn turn_tile(&mut self, window_id: WindowId, turns: u8, mirror: bool) {
+        let Some(position) = self.tile_under_cursor(window_id) else {
+            return;
+        };
+        let tile = &mut self.tiles.tiles[position];
+        if mirror {
+            tile.mirrored = !tile.mirrored;
+            tile.turns = (4 - tile.turns) % 4;
+        }
+        tile.turns = (tile.turns + turns) % 4;
+        println!("position {position} has tile {tile}");
+        self.apply_tiles();
+    }
+    /// Render the shader `filter::FILTER_FILE` names, its red channel comes back as
+    /// `Event::ShaderMask`
+    fn refresh_shader_mask(&mut self) {
+        let Some(texture) = self
+            .filter
+            .shader
+            .as_ref()
+            .and_then(|shader| self.render_shader(shader, bits::BITS_FORMAT))
+        else {
+            return;
+        };
+        let pixels = export::read_texture(&self.gpu.device, &self.gpu.queue, &texture);
+        let sender = self.platform.event_sender();
+        let reporter = self.platform.error_reporter();
+        Platform::run_future(async move {
+            match pixels.await {
+                Ok(pixels) => {
+                    let mask = pixels.chunks_exact(4).map(|p| p[0] as f32 / 255.0);
+                    sender.send_event(Event::ShaderMask(mask.collect()));
+                }
+                Err(err) => reporter(Box::new(err)),
+            }
         });
-        let buffer_binding = self.data_buffer.as_entire_buffer_binding();
-
-        self.bind_group = Some(self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler1),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&sampler2),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer(buffer_binding),
-                },
+    }
+    /// Paint a notch where the cursor is over a spectrum, unless the last one is close by
+    fn paint_notch(&mut self, window_id: WindowId) {
+        let Some(window) = self.windows.get(&window_id) else {
+            return;
+        };
+        if window.viewports[window.focused].spectrum.is_none() {
+            return;
+        }
+        let Some(pixel) = window.cursor_pixel(self.img_dim) else {
+            return;
+        };
+        let (fx, fy) = spectrum::frequency(pixel, self.img_dim);
+        let r = self.filter.brush;
+        if let Some(filter::Shape::Notch { fx: x, fy: y, .. }) = self.painted.last() {
+            if (fx - x).hypot(fy - y) < r / 2.0 {
+                return;
+            }
+        }
+        self.painted.push(filter::Shape::Notch { fx, fy, r });
+        self.update_mask();
+    }
+    /// Count a window's histogram again, the counts come back as `Event::Histogram`
+    fn refresh_histogram(&mut self, window_id: WindowId) {
+        let Some(window) = self.windows.get(&window_id) else {
+            return;
+        };
+        let (Some(raw), Some(raw_view)) = (self.raw.clone(), &self.raw_view) else {
+            return;
+        };
+        let region = match (window.histogram.region, window.histogram.selection) {
+            // a different image may have loaded since
+            (true, Some([x0, y0, x1, y1])) => [
+                x0.min(raw.width),
+                y0.min(raw.height),
+                x1.min(raw.width),
+                y1.min(raw.height),
             ],
-            label: None,
-        }));
+            (true, None) => window.visible_region(self.img_dim),
+            (false, _) => [0, 0, raw.width, raw.height],
+        };
+        let output = match window.histogram.output {
+            true => match self.render_focused(window_id, bits::BITS_FORMAT) {
+                Some(texture) => Some(texture),
+                None => return,
+            },
+            false => None,
+        };
+        let (device, queue) = (&self.gpu.device, &self.gpu.queue);
+        let counts: Pin<Box<dyn Future<Output = Result<_, wgpu::BufferAsyncError>>>> =
+            match (&self.gpu.histogram_counter, output) {
+                (Some(counter), Some(texture)) => {
+                    let view = texture.create_view(&Default::default());
+                    Box::pin(counter.count(device, queue, Source::Output(&view), region))
+                }
+                (Some(counter), None) => {
+                    Box::pin(counter.count(device, queue, Source::Raw(raw_view, raw.depth), region))
+                }
+                (None, Some(texture)) => {
+                    let (width, height) = (texture.width(), texture.height());
+                    let pixels = export::read_texture(device, queue, &texture);
+                    Box::pin(async move {
+                        let output = RawImage {
+                            width,
+                            height,
+                            depth: 8,
+                            values: pixels.await?.into_iter().map(u16::from).collect(),
+                        };
+                        Ok(histogram::count_cpu(&output, region))
+                    })
+                }
+                (None, None) => Box::pin(async move { Ok(histogram::count_cpu(&raw, region)) }),
+            };
+        let sender = self.platform.event_sender();
+        let reporter = self.platform.error_reporter();
+        Platform::run_future(async move {
+            match counts.await {
+                Ok(counts) => sender.send_event(Event::Histogram(window_id, counts)),
+                Err(err) => reporter(Box::new(err)),
+            }
+        });
+    }
+    /// Try every LSB spec in the background, see `scan_hits` for what happens after
+    fn scan(&mut self) {
+        let Some(raw) = self.raw.clone() else {
+            return;
+        };
+        println!("scanning {} specs", steg::specs().len());
+        self.platform.scan(raw);
+    }
+    /// List the hits and make the best one what ctrl+L saves
+    fn scan_hits(&mut self, hits: Vec<steg::Hit>) {
+        println!("{} hits", hits.len());
+        for hit in hits.iter().take(SCAN_HITS_SHOWN) {
+            println!("{hit}");
+        }
+        if let Some(best) = hits.into_iter().next() {
+            self.lsb = best.spec;
+            self.preview_lsb();
+        }
+    }
+    /// Save exactly what a window shows, overlays and all
+    fn screenshot(&mut self, window_id: WindowId) {
+        let Some(window) = self.windows.get(&window_id) else {
+            return;
+        };
+        let texture = window.screenshot(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.pipelines,
+            self.img_dim,
+            self.lod_bias,
+        );
+        let size = (texture.width(), texture.height());
+        let pixels = export::read_texture(&self.gpu.device, &self.gpu.queue, &texture);
+        let format = texture.format();
+        let name = format!("screenshot-{}.png", Platform::timestamp());
+        let reporter = self.platform.error_reporter();
+        Platform::run_future(async move {
+            let result = match pixels.await {
+                Ok(pixels) => {
+                    export::encode_png(size, export::window_rgba(format, pixels)).map_err(Box::from)
+                }
+                Err(err) => Err(Box::from(err)),
+            };
+            match result.and_then(|png| Platform::save_file(&name, &png)) {
+                Ok(()) => println!("saved {name}"),
+                Err(err) => reporter(err),
+            }
+        });
+    }
+    fn bundle(&self) -> session::Bundle {
+        let mut settings = BTreeMap::from([
+            (filter::FILTER_FILE.to_owned(), self.filter.to_string()),
+            (lsb::SPEC_FILE.to_owned(), self.lsb.to_string()),
+            (bits::PACKING_FILE.to_owned(), self.packing.to_string()),
+        ]);
+        if self.tiled {
+            settings.insert(tiles::TILES_FILE.to_owned(), self.tiles.to_string());
+        }
+        session::Bundle {
+            version: session::BUNDLE_VERSION,
+            images: self.image_hashes.clone(),
+            shaders: self.sources.clone().into_iter().collect(),
+            settings,
+            notes: self.notes.clone(),
+            windows: self.windows.values().map(AppWindow::state).collect(),
+            mipmaps: self.mipmaps,
+            lod_bias: self.lod_bias,
+            painted: self.painted.clone(),
+            ela: self.ela,
+            compare_mode: self.compare_mode,
+            compare_offset: self.compare_offset,
+        }
+    }
+    fn save_bundle(&mut self) {
+        let name = format!(
+            "session-{}{}",
+            Platform::timestamp(),
+            session::BUNDLE_SUFFIX
+        );
+        let result = serde_json::to_vec_pretty(&self.bundle())
+            .map_err(Box::from)
+            .and_then(|json| Platform::save_file(&name, &json));
+        match result {
+            Ok(()) => println!("saved {name}"),
+            Err(err) => self.platform.error_reporter()(err),
+        }
+    }
+    /// Ask the platform for the newest bundle it can see, it comes back as `FileContents`. On the
+    /// web that means one dropped on the page or picked, which is also restored straight away
+    fn open_bundle(&mut self) {
+        let newest = self
+            .platform
+            .list_files()
+            .into_iter()
+            .filter(|name| name.ends_with(session::BUNDLE_SUFFIX))
+            .max();
+        match newest {
+            Some(name) => {
+                println!("opening {name}");
+                self.platform.watch_file(&name);
+            }
+            None => println!("no *{} files found", session::BUNDLE_SUFFIX),
+        }
+    }
+    fn restore(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        bundle: session::Bundle,
+    ) {
+        let reporter = self.platform.error_reporter();
+        if bundle.version != session::BUNDLE_VERSION {
+            return reporter(format!("can't read version {} bundles", bundle.version).into());
+        }
+        for (name, hash) in &bundle.images {
+            match self.image_hashes.get(name) {
+                Some(ours) if ours == hash => {}
+                Some(_) => reporter(format!("{name} differs from the one in the bundle").into()),
+                None => reporter(format!("{name} from the bundle isn't loaded").into()),
+            }
+        }
+        for (name, code) in &bundle.shaders {
+            self.load_shader(name, code);
+        }
+        self.lod_bias = bundle.lod_bias;
+        if self.mipmaps != bundle.mipmaps {
+            self.mipmaps = bundle.mipmaps;
+            self.update_bind_groups();
+        }
+        self.painted = bundle.painted;
+        self.ela = bundle.ela;
+        self.compare_mode = bundle.compare_mode;
+        self.compare_offset = bundle.compare_offset;
+        // windows are interchangeable, so reuse whichever exist and open or close the difference
+        let mut ids: Vec<_> = self.windows.keys().copied().collect();
+        let mut spectra = Vec::new();
+        for state in &bundle.windows {
+            let id = match ids.pop() {
+                Some(id) => id,
+                None => {
+                    let viewport = self.new_viewport(View::default(), String::new());
+                    self.open_window(event_loop, viewport)
+                }
+            };
+            let texture_view = self.texture_view.as_ref();
+            let raw_view = self.raw_view.as_ref();
+            let window = self.windows.get_mut(&id).unwrap();
+            window.restore(state, |view, shader| {
+                self.gpu.new_viewport(view, shader, texture_view, raw_view)
+            });
+            for (index, viewport) in state.viewports.iter().enumerate() {
+                if let Some(spectrum) = viewport.spectrum {
+                    spectra.push((id, index, spectrum));
+                }
+            }
+        }
+        for id in ids {
+            self.windows.remove(&id);
+        }
+        if let Some(raw) = &self.raw {
+            let spectrum = self
+                .spectrum
+                .get_or_insert_with(|| Arc::new(Spectrum::new(raw)))
+                .clone();
+            for (id, index, (shown, output)) in spectra {
+                let Some(viewport) = self
+                    .windows
+                    .get_mut(&id)
+                    .and_then(|window| window.viewports.get_mut(index))
+                else {
+                    continue;
+                };
+                // the image's spectrum stands in for the shader output's until that comes back,
+                // which keeps what part of it is shown
+                self.gpu
+                    .show_spectrum(viewport, spectrum.clone(), shown, false, None);
+                if output {
+                    self.refresh_spectrum(id, index, true);
+                }
+            }
+        }
+        // settings files go through the same handling as when they're edited
+        for (name, contents) in bundle.settings {
+            self.user_event(event_loop, Event::FileContents(name, contents.into_bytes()));
+        }
+        if !bundle.notes.is_empty() {
+            println!("notes\n{}", bundle.notes);
+        }
+        self.notes = bundle.notes;
+        self.filtered |= bundle
+            .windows
+            .iter()
+            .flat_map(|state| &state.viewports)
+            .any(|viewport| viewport.result);
+        self.update_filter();
+        self.update_ela();
+        self.update_comparison();
+        for window in self.windows.values() {
+            window.update_title(self.img_dim);
+        }
+        for state in &bundle.windows {
+            for viewport in &state.viewports {
+                if !self.pipelines.contains_key(&viewport.shader) {
+                    self.platform.watch_file(&viewport.shader);
+                }
+            }
+        }
+        self.request_redraw_all();
+    }
+    fn request_redraw_all(&self) {
+        for window in self.windows.values() {
+            window.window.request_redraw();
+        }
+    }
+    fn key_pressed(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        window_id: WindowId,
+        c: winit::keyboard::KeyCode,
+    ) {
+        // keys that affect more than one window go here, the rest in `window_key`
+        match c {
+            winit::keyboard::KeyCode::KeyM => {
+                self.mipmaps = !self.mipmaps;
+                println!("mipmaps {}", self.mipmaps);
+                self.update_bind_groups();
+            }
+            winit::keyboard::KeyCode::BracketLeft => {
+                self.lod_bias -= 0.5;
+                println!("lod bias {}", self.lod_bias);
+            }
+            winit::keyboard::KeyCode::BracketRight => {
+                self.lod_bias += 0.5;
+                println!("lod bias {}", self.lod_bias);
+            }
+            winit::keyboard::KeyCode::KeyN => {
+                if let Some(viewport) = self.clone_viewport(window_id) {
+                    self.open_window(event_loop, viewport);
+                }
+            }
+            winit::keyboard::KeyCode::KeyT if !self.modifiers.shift_key() => {
+                let Some(viewport) = self.clone_viewport(window_id) else {
+                    return;
+                };
+                let window = self.windows.get_mut(&window_id).unwrap();
+                window.viewports.push(viewport);
+            }
+            winit::keyboard::KeyCode::KeyS if self.modifiers.control_key() => {
+                return self.export(window_id, self.modifiers.shift_key());
+            }
+            winit::keyboard::KeyCode::KeyB if self.modifiers.control_key() => {
+                return self.extract_bits(window_id);
+            }
+            winit::keyboard::KeyCode::KeyL
+                if self.modifiers.control_key() && self.modifiers.shift_key() =>
+            {
+                return self.scan();
+            }
+            winit::keyboard::KeyCode::KeyL if self.modifiers.control_key() => {
+                return self.save_lsb();
+            }
+            winit::keyboard::KeyCode::KeyE if self.modifiers.control_key() => {
+                return self.save_bundle();
+            }
+            winit::keyboard::KeyCode::KeyO if self.modifiers.control_key() => {
+                return self.open_bundle();
+            }
+            winit::keyboard::KeyCode::KeyU if self.modifiers.shift_key() => {
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                window.isolate = None;
+            }
+            winit::keyboard::KeyCode::KeyU => return self.list_colors(),
+            winit::keyboard::KeyCode::Comma => return self.step_color(window_id, -1),
+            winit::keyboard::KeyCode::Period => return self.step_color(window_id, 1),
+            winit::keyboard::KeyCode::Minus | winit::keyboard::KeyCode::Equal
+                if self.modifiers.control_key() =>
+            {
+                // about one 8-bit level, whatever the depth
+                let step = 1 << (self.bit_depth() - 8);
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let Some(isolate) = &mut window.isolate else {
+                    return;
+                };
+                isolate.tolerance = if c == winit::keyboard::KeyCode::Minus {
+                    isolate.tolerance.saturating_sub(step)
+                } else {
+                    isolate.tolerance.saturating_add(step)
+                };
+                let color = isolate.color;
+                return self.isolate(window_id, color);
+            }
+            winit::keyboard::KeyCode::KeyZ => {
+                return self.toggle_spectrum(window_id, self.modifiers.shift_key())
+            }
+            winit::keyboard::KeyCode::KeyD if self.modifiers.shift_key() => {
+                println!("cleared {} painted notches", self.painted.len());
+                self.painted.clear();
+                return self.update_filter();
+            }
+            winit::keyboard::KeyCode::KeyD => {
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let viewport = window.focused_mut();
+                viewport.result = !viewport.result;
+                if viewport.result {
+                    println!("filter\n{}", self.filter);
+                    self.apply_filter();
+                }
+            }
+            winit::keyboard::KeyCode::KeyA
+                if self.modifiers.control_key() || self.modifiers.alt_key() =>
+            {
+                let down = self.modifiers.shift_key();
+                if self.modifiers.control_key() {
+                    let quality = self.ela.quality as i32 + if down { -5 } else { 5 };
+                    self.ela.quality = quality.clamp(1, 100) as u8;
+                } else {
+                    self.ela.scale *= if down { 0.5 } else { 2.0 };
+                }
+                println!("ELA quality {} scale {}", self.ela.quality, self.ela.scale);
+                return self.update_ela();
+            }
+            winit::keyboard::KeyCode::KeyA => {
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let viewport = window.focused_mut();
+                viewport.ela = viewport.ela.cycle();
+                println!("ELA {}", viewport.ela.label());
+                window.update_title(self.img_dim);
+                return self.update_ela();
+            }
+            winit::keyboard::KeyCode::Backquote if self.modifiers.control_key() => {
+                // swap the slots, so the shader and everything else work on the other image
+                let Some(b) = self.compare_raw.take() else {
+                    return;
+                };
+                self.compare_raw = self.raw.clone();
+                println!("swapped the image with {}", compare::SLOT_B_FILE);
+                self.load_image(b.to_image());
+                self.compare_offset = (-self.compare_offset.0, -self.compare_offset.1);
+                return self.update_comparison();
+            }
+            winit::keyboard::KeyCode::Backquote if self.modifiers.alt_key() => {
+                self.compare_offset = (0, 0);
+                return self.update_comparison();
+            }
+            winit::keyboard::KeyCode::Backquote if self.modifiers.shift_key() => {
+                self.compare_mode = self.compare_mode.cycle();
+                return self.update_comparison();
+            }
+            winit::keyboard::KeyCode::Backquote => {
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let viewport = window.focused_mut();
+                viewport.compare = !viewport.compare;
+                if self.compare_raw.is_none() {
+                    println!(
+                        "nothing to compare with, save it as {}",
+                        compare::SLOT_B_FILE
+                    );
+                }
+                return self.update_comparison();
+            }
+            winit::keyboard::KeyCode::ArrowLeft
+            | winit::keyboard::KeyCode::ArrowRight
+            | winit::keyboard::KeyCode::ArrowUp
+            | winit::keyboard::KeyCode::ArrowDown
+                if self.modifiers.alt_key() =>
+            {
+                let step = if self.modifiers.shift_key() { 10 } else { 1 };
+                let (dx, dy) = match c {
+                    winit::keyboard::KeyCode::ArrowLeft => (-step, 0),
+                    winit::keyboard::KeyCode::ArrowRight => (step, 0),
+                    winit::keyboard::KeyCode::ArrowUp => (0, -step),
+                    _ => (0, step),
+                };
+                self.compare_offset.0 += dx;
+                self.compare_offset.1 += dy;
+                return self.update_comparison();
+            }
+            winit::keyboard::KeyCode::Semicolon => {
+                let estimate = self.raw.as_deref().and_then(stereo::estimate_period);
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let viewport = window.focused_mut();
+                // shift re-estimates without turning the decoder off
+                viewport.stereo = match (viewport.stereo, self.modifiers.shift_key()) {
+                    (Some(_), false) => None,
+                    (stereo, _) => {
+                        let Some(period) = estimate else {
+                            println!("no repeat found in the image");
+                            return;
+                        };
+                        Some(Stereo {
+                            period,
+                            ..stereo.unwrap_or(Stereo::new(period))
+                        })
+                    }
+                };
+                if let Some(stereo) = viewport.stereo {
+                    println!("{}", stereo.label());
+                }
+                window.update_title(self.img_dim);
+            }
+            winit::keyboard::KeyCode::Quote => {
+                let down = self.modifiers.shift_key();
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let Some(stereo) = &mut window.focused_mut().stereo else {
+                    return;
+                };
+                if self.modifiers.alt_key() {
+                    stereo.range = (stereo.range + if down { -1.0 } else { 1.0 })
+                        .clamp(0.0, stereo::MAX_RANGE);
+                } else {
+                    let step = if self.modifiers.control_key() {
+                        0.25
+                    } else {
+                        1.0
+                    };
+                    stereo.period = (stereo.period + if down { -step } else { step }).max(1.0);
+                }
+                println!("{}", stereo.label());
+                window.update_title(self.img_dim);
+            }
+            winit::keyboard::KeyCode::Backslash if self.modifiers.control_key() => {
+                let text = self.tiles.to_string();
+                match Platform::save_file(tiles::TILES_FILE, text.as_bytes()) {
+                    Ok(()) => println!("saved {}\n{text}", tiles::TILES_FILE),
+                    Err(err) => self.platform.error_reporter()(err),
+                }
+                return;
+            }
+            winit::keyboard::KeyCode::Backslash if self.modifiers.shift_key() => {
+                self.tiles.tiles.clear();
+                println!("tiles back in place");
+                return self.apply_tiles();
+            }
+            winit::keyboard::KeyCode::Backslash => {
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let viewport = window.focused_mut();
+                viewport.tiles = !viewport.tiles;
+                if viewport.tiles {
+                    println!("tiles\n{}", self.tiles);
+                    return self.apply_tiles();
+                }
+                window.update_title(self.img_dim);
+            }
+            winit::keyboard::KeyCode::Slash => {
+                let (turns, mirror) =
+                    match (self.modifiers.control_key(), self.modifiers.shift_key()) {
+                        (true, _) => (0, true),
+                        (false, false) => (1, false),
+                        (false, true) => (3, false),
+                    };
+                return self.turn_tile(window_id, turns, mirror);
+            }
+            winit::keyboard::KeyCode::PageUp | winit::keyboard::KeyCode::PageDown => {
+                let Some(raw) = &self.raw else {
+                    return;
+                };
+                let dim = (raw.width, raw.height);
+                let (tile, grid) = self.tiles.size.resolve(dim);
+                // step from what was asked for, the grid can come out smaller
+                let (cols, rows) = match self.tiles.size {
+                    tiles::Size::Grid(cols, rows) => (cols, rows),
+                    tiles::Size::Tile(..) => grid,
+                };
+                let (tw, th) = match self.tiles.size {
+                    tiles::Size::Tile(tw, th) => (tw, th),
+                    tiles::Size::Grid(..) => tile,
+                };
+                let up = c == winit::keyboard::KeyCode::PageUp;
+                let step = |n: u32| {
+                    if up {
+                        n + 1
+                    } else {
+                        n.saturating_sub(1).max(1)
+                    }
+                };
+                self.tiles.size = match (self.modifiers.control_key(), self.modifiers.shift_key()) {
+                    (false, false) => tiles::Size::Grid(step(cols), rows),
+                    (false, true) => tiles::Size::Grid(cols, step(rows)),
+                    (true, false) => tiles::Size::Tile(step(tw), th),
+                    (true, true) => tiles::Size::Tile(tw, step(th)),
+                };
+                self.tiles.tiles.clear();
+                println!("{}", self.tiles.size);
+                return self.apply_tiles();
+            }
+            winit::keyboard::KeyCode::Home | winit::keyboard::KeyCode::End => {
+                let up = c == winit::keyboard::KeyCode::End;
+                let step = |n: usize| if up { n * 2 } else { n / 2 };
+                let settings = &mut self.spectrogram;
+                match (self.modifiers.control_key(), self.modifiers.shift_key(), up) {
+                    (true, _, false) => settings.function = settings.function.cycle(),
+                    (true, _, true) => settings.log = !settings.log,
+                    (false, true, _) => settings.hop = step(settings.hop).max(1),
+                    (false, false, _) => settings.window = step(settings.window).clamp(16, 1 << 16),
+                }
+                return self.load_spectrogram();
+            }
+            winit::keyboard::KeyCode::KeyY => {
+                let shift = self.modifiers.shift_key();
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let viewport = window.focused_mut();
+                let Some(layer) = &viewport.spectrum else {
+                    return;
+                };
+                let (spectrum, mut shown, output) =
+                    (layer.spectrum.clone(), layer.shown, layer.output);
+                match shift {
+                    true => shown.step_channel(1),
+                    false => shown.toggle_component(),
+                }
+                println!("spectrum {}", shown.label());
+                let mask = self.mask.as_deref().filter(|_| !output);
+                self.gpu
+                    .show_spectrum(viewport, spectrum, shown, output, mask);
+                window.update_title(self.img_dim);
+            }
+            winit::keyboard::KeyCode::KeyI => {
+                let (control, alt, shift) = (
+                    self.modifiers.control_key(),
+                    self.modifiers.alt_key(),
+                    self.modifiers.shift_key(),
+                );
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let histogram = &mut window.histogram;
+                if control {
+                    histogram.log = !histogram.log;
+                    println!("histogram log scale {}", histogram.log);
+                    histogram.upload(&self.gpu.queue);
+                } else if alt {
+                    histogram.region = !histogram.region;
+                    match (histogram.region, histogram.selection) {
+                        (true, Some(selection)) => println!("histogram of {selection:?}"),
+                        (true, None) => println!("histogram of visible region"),
+                        (false, _) => println!("histogram of whole image"),
+                    }
+                    self.refresh_histogram(window_id);
+                } else if histogram.counts.is_some() && histogram.output == shift {
+                    histogram.counts = None;
+                } else {
+                    histogram.output = shift;
+                    self.refresh_histogram(window_id);
+                }
+            }
+            winit::keyboard::KeyCode::Digit1
+            | winit::keyboard::KeyCode::Digit2
+            | winit::keyboard::KeyCode::Digit3
+            | winit::keyboard::KeyCode::Digit4
+            | winit::keyboard::KeyCode::Digit5 => {
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let c = match c {
+                    winit::keyboard::KeyCode::Digit1 => 0,
+                    winit::keyboard::KeyCode::Digit2 => 1,
+                    winit::keyboard::KeyCode::Digit3 => 2,
+                    winit::keyboard::KeyCode::Digit4 => 3,
+                    _ => 4,
+                };
+                window.histogram.channels[c] ^= true;
+                window.histogram.upload(&self.gpu.queue);
+            }
+            winit::keyboard::KeyCode::KeyF => {
+                self.float_format = self.float_format.cycle();
+                println!("float export format {}", self.float_format.extension());
+                return;
+            }
+            winit::keyboard::KeyCode::KeyP => return self.screenshot(window_id),
+            winit::keyboard::KeyCode::KeyS => {
+                // cycle the focused viewport through the shader files we can see
+                let mut shaders = self
+                    .platform
+                    .list_files()
+                    .into_iter()
+                    .filter(|name| name.ends_with(".wgsl"))
+                    .collect::<Vec<_>>();
+                shaders.sort();
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                let viewport = window.focused_mut();
+                let next = shaders
+                    .iter()
+                    .position(|name| *name == viewport.shader)
+                    .map_or(0, |i| (i + 1) % shaders.len());
+                let Some(shader) = shaders.get(next) else {
+                    return;
+                };
+                println!("shader {shader}");
+                viewport.shader.clone_from(shader);
+                if !self.pipelines.contains_key(shader) {
+                    self.platform.watch_file(shader);
+                }
+                window.update_title(self.img_dim);
+            }
+            _ => return self.window_key(window_id, c),
+        }
+        self.request_redraw_all();
+    }
+    /// Keys that only affect one window or its focused viewport
+    fn window_key(&mut self, window_id: WindowId, c: winit::keyboard::KeyCode) {
+        let img_dim = self.img_dim;
+        let bit_depth = self.bit_depth();
+        let shift = self.modifiers.shift_key();
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+        let view = &mut window.viewports[window.focused].view;
+        match c {
+            winit::keyboard::KeyCode::ArrowLeft => {
+                view.pan(0.1 / view.scale, 0.0, img_dim);
+            }
+            winit::keyboard::KeyCode::ArrowRight => {
+                view.pan(-0.1 / view.scale, 0.0, img_dim);
+            }
+            winit::keyboard::KeyCode::ArrowUp => {
+                view.pan(0.0, -0.1 / view.scale, img_dim);
+            }
+            winit::keyboard::KeyCode::ArrowDown => {
+                view.pan(0.0, 0.1 / view.scale, img_dim);
+            }
+            winit::keyboard::KeyCode::KeyR => {
+                view.rotate(if shift { -90.0 } else { 90.0 });
+                println!("rotation {}", view.rotation);
+            }
+            winit::keyboard::KeyCode::KeyQ | winit::keyboard::KeyCode::KeyE => {
+                let step = if shift { 0.5 } else { 5.0 };
+                view.rotate(if c == winit::keyboard::KeyCode::KeyQ {
+                    -step
+                } else {
+                    step
+                });
+                println!("rotation {}", view.rotation);
+            }
+            winit::keyboard::KeyCode::KeyH => view.flip_horizontal(),
+            winit::keyboard::KeyCode::KeyV => view.flip_vertical(),
+            winit::keyboard::KeyCode::KeyT => {
+                // shift+T, close the focused viewport unless it's the last one
+                if window.viewports.len() > 1 {
+                    window.viewports.remove(window.focused);
+                    window.focused = window.focused.min(window.viewports.len() - 1);
+                }
+            }
+            winit::keyboard::KeyCode::KeyB => {
+                println!("background {}", window.background.next_color());
+            }
+            winit::keyboard::KeyCode::KeyC => {
+                window.background.checkerboard = !window.background.checkerboard;
+            }
+            winit::keyboard::KeyCode::KeyO => {
+                window.background.outline = !window.background.outline;
+            }
+            winit::keyboard::KeyCode::KeyW => {
+                window.split.shape = WipeShape::cycle(window.split.shape);
+                if window.split.shape == Some(WipeShape::Vertical) {
+                    let size = window.window.inner_size();
+                    window.split.pos = (size.width as f32 / 2.0, size.height as f32 / 2.0);
+                }
+                println!("split {:?}", window.split.shape);
+            }
+            winit::keyboard::KeyCode::KeyX => {
+                window.split.swapped = !window.split.swapped;
+            }
+            winit::keyboard::KeyCode::Minus => {
+                window.split.radius = (window.split.radius / 1.25).max(8.0);
+            }
+            winit::keyboard::KeyCode::Equal => {
+                window.split.radius *= 1.25;
+            }
+            winit::keyboard::KeyCode::KeyL => {
+                let plane = &mut window.viewports[window.focused].plane;
+                *plane = match plane {
+                    Some(_) => None,
+                    None => Some(Plane::new(bit_depth)),
+                };
+                window.update_title(img_dim);
+            }
+            winit::keyboard::KeyCode::KeyJ
+            | winit::keyboard::KeyCode::KeyK
+            | winit::keyboard::KeyCode::KeyG => {
+                let Some(plane) = &mut window.viewports[window.focused].plane else {
+                    return;
+                };
+                let step = if c == winit::keyboard::KeyCode::KeyJ {
+                    -1
+                } else {
+                    1
+                };
+                match c {
+                    winit::keyboard::KeyCode::KeyG => plane.grid = !plane.grid,
+                    _ if shift => plane.step_channel(step),
+                    _ => plane.step_bit(step),
+                }
+                window.update_title(img_dim);
+            }
+            _ => return,
+        }
+        window.window.request_redraw();
     }
     fn new(
         event_loop: &winit::event_loop::ActiveEventLoop,
         mut platform: Platform,
     ) -> impl 'static + Future<Output = Self> {
-        let window = event_loop
-            .create_window(Platform::set_window_attrs(
-                winit::window::WindowAttributes::default(),
-            ))
-            .unwrap();
-        let mut size = window.inner_size();
-        size.width = size.width.max(640);
-        size.height = size.height.max(480);
+        let window = Arc::new(
+            event_loop
+                .create_window(Platform::set_window_attrs(
+                    winit::window::WindowAttributes::default().with_title(TITLE),
+                ))
+                .unwrap(),
+        );
         let instance = wgpu::Instance::default();
         platform.watch_file("nuero.png");
         platform.watch_file("shader.wgsl");
+        platform.watch_file(bits::PACKING_FILE);
+        platform.watch_file(session::NOTES_FILE);
+        platform.watch_file(lsb::SPEC_FILE);
+        platform.watch_file(filter::FILTER_FILE);
+        platform.watch_file(compare::SLOT_B_FILE);
+        platform.watch_file(tiles::TILES_FILE);
+        platform.watch_file(audio::SETTINGS_FILE);
+        for name in audio::AUDIO_FILES {
+            platform.watch_file(name);
+        }
 
         async move {
-            // XXX: I hate this, can't this be an Rc?
-            let window = Box::leak(Box::new(window));
-            let surface = instance.create_surface(&*window).unwrap();
-            let adapter = instance
-                .request_adapter(&wgpu::RequestAdapterOptions {
-                    power_preference: wgpu::PowerPreference::default(),
-                    force_fallback_adapter: false,
-                    // Request an adapter which can render to our surface
-                    compatible_surface: Some(&surface),
-                })
+            let surface = instance.create_surface(window.clone()).unwrap();
+            let gpu = Gpu::new(instance, Some(&surface), false)
                 .await
                 .expect("Failed to find an appropriate adapter");
-
-            // Create the logical device and command queue
-            let (device, queue) = adapter
-                .request_device(
-                    &wgpu::DeviceDescriptor {
-                        label: None,
-                        required_features: wgpu::Features::default(),
-                        // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                        required_limits: wgpu::Limits::downlevel_webgl2_defaults()
-                            .using_resolution(adapter.limits()),
-                        memory_hints: wgpu::MemoryHints::MemoryUsage,
-                    },
-                    None,
-                )
-                .await
-                .expect("Failed to create device");
-
-            let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
-                label: None,
-            });
-            let data_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-                label: None,
-                mapped_at_creation: false,
-                size: 32,
-                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            });
             let reporter = platform.error_reporter();
-            device.on_uncaptured_error(Box::new(move |error: wgpu::Error| {
-                reporter(Box::new(error))
-            }));
+            gpu.device
+                .on_uncaptured_error(Box::new(move |error: wgpu::Error| {
+                    reporter(Box::new(error))
+                }));
 
-            let config = surface
-                .get_default_config(&adapter, size.width, size.height)
-                .unwrap();
-            surface.configure(&device, &config);
-            Self {
-                config,
-                device,
-                adapter,
-                queue,
-                render_pipeline: None,
-                surface,
+            let viewport = gpu.new_viewport(View::default(), "shader.wgsl".to_owned(), None, None);
+            let window = AppWindow::new(
+                &gpu.device,
+                &gpu.adapter,
+                &gpu.layout,
+                &gpu.plane_layout,
                 window,
-                _platform: platform,
-                bind_group: None,
-                layout,
-                data_buffer,
+                surface,
+                viewport,
+            );
+            Self {
+                gpu,
+                format: window.format(),
+                windows: HashMap::from([(window.window.id(), window)]),
+                platform,
+                modules: HashMap::new(),
+                sources: HashMap::new(),
+                image_hashes: BTreeMap::new(),
+                pipelines: HashMap::new(),
+                texture: None,
+                texture_view: None,
+                raw_view: None,
+                raw: None,
                 img_dim: (0., 0.),
-                pos: (0.0, 0.0),
-                scale: 1.0,
+                modifiers: Default::default(),
+                mipmaps: true,
+                lod_bias: 0.0,
+                float_format: FloatFormat::Exr,
+                packing: Default::default(),
+                notes: String::new(),
+                lsb: Default::default(),
+                colors: None,
+                spectrum: None,
+                filter: Default::default(),
+                painted: Vec::new(),
+                shader_mask: None,
+                mask: None,
+                filtered: false,
+                ela: Default::default(),
+                ela_texture: None,
+                ela_view: None,
+                compare_raw: None,
+                compare_mode: Default::default(),
+                compare_offset: (0, 0),
+                comparison: None,
+                compare_view: None,
+                tiles: Default::default(),
+                tiled: false,
+                audio: None,
+                spectrogram: Default::default(),
             }
         }
     }
 }
 
+/// Pipeline drawing a 4 vertex triangle strip with the shared bind group layout
+fn create_quad_pipeline(
+    device: &Device,
+    layout: &BindGroupLayout,
+    shader: &wgpu::ShaderModule,
+    vs_entry: &str,
+    fs_entry: &str,
+    format: wgpu::TextureFormat,
+    blend: Option<wgpu::BlendState>,
+) -> RenderPipeline {
+    create_quad_pipeline_with_constants(
+        device,
+        layout,
+        shader,
+        vs_entry,
+        fs_entry,
+        format,
+        blend,
+        &HashMap::new(),
+    )
+}
+
+/// Like `create_quad_pipeline`, with values for the shader's `override` declarations
+#[allow(clippy::too_many_arguments)]
+fn create_quad_pipeline_with_constants(
+    device: &Device,
+    layout: &BindGroupLayout,
+    shader: &wgpu::ShaderModule,
+    vs_entry: &str,
+    fs_entry: &str,
+    format: wgpu::TextureFormat,
+    blend: Option<wgpu::BlendState>,
+    constants: &HashMap<String, f64>,
+) -> RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: vs_entry,
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants,
+                ..Default::default()
+            },
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fs_entry,
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants,
+                ..Default::default()
+            },
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
 #[derive(Debug)]
 enum Event {
     // Redraw,
     FileContents(String, Vec<u8>),
+    /// Results of a steg scan, best first
+    ScanHits(Vec<steg::Hit>),
+    /// New counts for a window's histogram
+    Histogram(WindowId, histogram::Counts),
+    /// Spectrum of a viewport's shader output, by index in its window
+    Spectrum(WindowId, usize, Arc<Spectrum>),
+    /// Red channel of the frequency filter's mask shader
+    ShaderMask(Vec<f32>),
 }
 
 impl ApplicationHandler<Event> for App {
@@ -311,123 +1881,301 @@ impl ApplicationHandler<Event> for App {
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
-        _window_id: winit::window::WindowId,
+        window_id: WindowId,
         event: WindowEvent,
     ) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. }
+                if event.state != winit::event::ElementState::Released =>
+            {
+                match event.physical_key {
+                    winit::keyboard::PhysicalKey::Code(c) => {
+                        self.key_pressed(event_loop, window_id, c)
+                    }
+                    _ => {}
+                }
+                return;
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                return;
+            }
+            WindowEvent::MouseInput {
+                state: winit::event::ElementState::Pressed,
+                button: winit::event::MouseButton::Left,
+                ..
+            } if self.modifiers.control_key() => {
+                return self.pick_color(window_id);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: winit::event::MouseButton::Left,
+                ..
+            } if self.windows.get(&window_id).is_some_and(|window| {
+                window.viewports[window.focused].tiles && window.split.shape.is_none()
+            }) =>
+            {
+                return self.drag_tile(window_id, state.is_pressed());
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: winit::event::MouseButton::Right,
+                ..
+            } => {
+                let Some(window) = self.windows.get_mut(&window_id) else {
+                    return;
+                };
+                window.painting = state.is_pressed();
+                return match state.is_pressed() {
+                    true => self.paint_notch(window_id),
+                    // filtering again is too slow to do for every notch
+                    false => self.update_filter(),
+                };
+            }
+            WindowEvent::CloseRequested => {
+                self.windows.remove(&window_id);
+                if self.windows.is_empty() {
+                    event_loop.exit();
+                }
+                return;
+            }
+            _ => {}
+        }
+        let img_dim = self.img_dim;
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
         match event {
             WindowEvent::MouseWheel { delta, .. } => {
+                let view = &mut window.focused_mut().view;
                 match delta {
                     winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                        self.scale *= 1.1f32.powf(y);
+                        view.scale *= 1.1f32.powf(y);
                         // println!("scale1 {:?}", self.scale);
                     }
                     winit::event::MouseScrollDelta::PixelDelta(delta) => {
-                        self.scale *= 1.1f32.powf(delta.y as f32 * 0.1);
+                        view.scale *= 1.1f32.powf(delta.y as f32 * 0.1);
                         // println!("scale2 {:?}", self.scale);
                     }
                 }
-                self.window.request_redraw();
+                window.window.request_redraw();
             }
             WindowEvent::PinchGesture { delta, .. } => {
-                self.scale *= 1.1f32.powf(delta as f32);
+                window.focused_mut().view.scale *= 1.1f32.powf(delta as f32);
                 // println!("scale2 {:?}", self.scale);
-                self.window.request_redraw();
+                window.window.request_redraw();
             }
             WindowEvent::PanGesture { delta, .. } => {
-                self.pos.0 += delta.x / 500. / self.scale;
-                self.pos.1 += delta.y / 500. / self.scale;
-                println!("pos {:?}", self.pos);
-                self.window.request_redraw();
-            }
-            WindowEvent::KeyboardInput { event, .. } => {
-                if event.state != winit::event::ElementState::Released {
-                    match event.physical_key {
-                        winit::keyboard::PhysicalKey::Code(c) => match c {
-                            winit::keyboard::KeyCode::ArrowLeft => {
-                                self.pos.0 += 0.1 / self.scale;
-                                self.window.request_redraw();
-                            }
-                            winit::keyboard::KeyCode::ArrowRight => {
-                                self.pos.0 -= 0.1 / self.scale;
-                                self.window.request_redraw();
-                            }
-                            winit::keyboard::KeyCode::ArrowUp => {
-                                self.pos.1 -= 0.1 / self.scale;
-                                self.window.request_redraw();
-                            }
-                            winit::keyboard::KeyCode::ArrowDown => {
-                                self.pos.1 += 0.1 / self.scale;
-                                self.window.request_redraw();
-                            }
-                            _ => {}
-                        },
-                        _ => {}
-                    }
+                let view = &mut window.focused_mut().view;
+                view.pan(
+                    delta.x / 500. / view.scale,
+                    delta.y / 500. / view.scale,
+                    img_dim,
+                );
+                println!("pos {:?}", view.pos);
+                window.window.request_redraw();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = (position.x as f32, position.y as f32);
+                window.move_cursor(Some(cursor));
+                if window.split.dragging {
+                    window.split.pos = cursor;
+                    window.window.request_redraw();
+                }
+                window.update_title(img_dim);
+                if window.painting {
+                    self.paint_notch(window_id);
                 }
             }
+            WindowEvent::MouseInput {
+                state,
+                button: winit::event::MouseButton::Left,
+                ..
+            } if self.modifiers.shift_key() || window.histogram.selecting.is_some() => {
+                let end = window.cursor_pixel(img_dim);
+                let histogram = &mut window.histogram;
+                let Some(end) = end else {
+                    histogram.selecting = None;
+                    return;
+                };
+                if state.is_pressed() {
+                    histogram.selecting = Some(end);
+                    return;
+                }
+                let Some(start) = histogram.selecting.take() else {
+                    return;
+                };
+                let selection = histogram::region_between(start, end, img_dim);
+                if selection[0] == selection[2] || selection[1] == selection[3] {
+                    return;
+                }
+                println!("histogram of {selection:?}");
+                histogram.selection = Some(selection);
+                histogram.region = true;
+                self.refresh_histogram(window_id);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: winit::event::MouseButton::Left,
+                ..
+            } => {
+                window.split.dragging = window.split.shape.is_some() && state.is_pressed();
+                if let (true, Some(cursor)) = (window.split.dragging, window.cursor) {
+                    window.split.pos = cursor;
+                    window.window.request_redraw();
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                window.move_cursor(None);
+                window.update_title(img_dim);
+            }
             WindowEvent::Resized(new_size) => {
                 // Reconfigure the surface with the new size
-                self.config.width = new_size.width.max(1);
-                self.config.height = new_size.height.max(1);
-                self.surface.configure(&self.device, &self.config);
+                window.resize(&self.gpu.device, new_size.width, new_size.height);
                 // On macos the window needs to be redrawn manually after resizing
-                self.window.request_redraw();
+                window.window.request_redraw();
             }
             WindowEvent::RedrawRequested => {
-                if let (Some(pipeline), Some(group)) = (&self.render_pipeline, &self.bind_group) {
-                    let frame = self
-                        .surface
-                        .get_current_texture()
-                        .expect("Failed to acquire next swap chain texture");
-                    self.queue
-                        .write_buffer(&self.data_buffer, 0, &self.buf_contents());
-                    let view = frame
-                        .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default());
-                    let mut encoder = self
-                        .device
-                        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-                    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: None,
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
-                                store: wgpu::StoreOp::Store,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                    });
-                    rpass.set_pipeline(pipeline);
-                    rpass.set_bind_group(0, group, &[]);
-                    rpass.draw(0..4, 0..1);
-                    drop(rpass);
-                    drop(view);
-                    self.queue.submit(Some(encoder.finish()));
-                    frame.present();
-                }
-            }
-            WindowEvent::CloseRequested => event_loop.exit(),
+                window.render(
+                    &self.gpu.device,
+                    &self.gpu.queue,
+                    &self.pipelines,
+                    img_dim,
+                    self.lod_bias,
+                );
+                window.update_title(img_dim);
+            }
             _ => {}
         };
     }
-    fn user_event(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop, event: Event) {
+    fn user_event(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, event: Event) {
         match event {
             // Event::Redraw => self.window.request_redraw(),
+            Event::ScanHits(hits) => self.scan_hits(hits),
+            Event::Histogram(window_id, counts) => {
+                if let Some(window) = self.windows.get_mut(&window_id) {
+                    window.histogram.set_counts(&self.gpu.queue, counts);
+                    window.window.request_redraw();
+                }
+            }
+            Event::Spectrum(window_id, index, spectrum) => {
+                self.show_spectrum(window_id, index, spectrum, true)
+            }
+            Event::ShaderMask(mask) => {
+                self.shader_mask = Some(mask);
+                self.update_filter();
+            }
             Event::FileContents(name, contents) => match name.as_str() {
                 "nuero.png" => {
+                    self.image_hashes
+                        .insert(name.clone(), session::sha256_hex(&contents));
                     if let Ok(img) = image::load_from_memory(&contents) {
                         self.load_image(img);
-                        self.window.request_redraw();
+                        self.request_redraw_all();
+                    }
+                }
+                compare::SLOT_B_FILE => match image::load_from_memory(&contents) {
+                    Ok(img) => {
+                        self.compare_raw = Some(Arc::new(RawImage::new(&img)));
+                        self.update_comparison();
+                    }
+                    Err(err) => self.platform.error_reporter()(Box::new(err)),
+                },
+                name if audio::AUDIO_FILES.contains(&name) => {
+                    self.image_hashes
+                        .insert(name.to_owned(), session::sha256_hex(&contents));
+                    match audio::decode(name, contents) {
+                        Ok(audio) => {
+                            self.audio = Some(audio);
+                            self.load_spectrogram();
+                        }
+                        Err(err) => self.platform.error_reporter()(Box::new(err)),
+                    }
+                }
+                audio::SETTINGS_FILE => {
+                    let settings = std::str::from_utf8(&contents)
+                        .map_err(|err| err.to_string())
+                        .and_then(str::parse);
+                    match settings {
+                        Ok(settings) => {
+                            self.spectrogram = settings;
+                            self.load_spectrogram();
+                        }
+                        Err(err) => self.platform.error_reporter()(err.into()),
+                    }
+                }
+                tiles::TILES_FILE => {
+                    let arrangement = std::str::from_utf8(&contents)
+                        .map_err(|err| err.to_string())
+                        .and_then(str::parse::<tiles::Arrangement>);
+                    match arrangement {
+                        Ok(arrangement) => {
+                            self.tiles = arrangement;
+                            self.apply_tiles();
+                        }
+                        Err(err) => self.platform.error_reporter()(err.into()),
+                    }
+                }
+                bits::PACKING_FILE => {
+                    let packing = std::str::from_utf8(&contents)
+                        .map_err(|err| err.to_string())
+                        .and_then(str::parse);
+                    match packing {
+                        Ok(packing) => {
+                            println!("ctrl+B packs bits as {packing}");
+                            self.packing = packing;
+                        }
+                        Err(err) => self.platform.error_reporter()(err.into()),
                     }
                 }
-                "shader.wgsl" => {
+                lsb::SPEC_FILE => {
+                    let spec = std::str::from_utf8(&contents)
+                        .map_err(|err| err.to_string())
+                        .and_then(str::parse);
+                    match spec {
+                        Ok(spec) => {
+                            self.lsb = spec;
+                            self.preview_lsb();
+                        }
+                        Err(err) => self.platform.error_reporter()(err.into()),
+                    }
+                }
+                session::NOTES_FILE => self.notes = String::from_utf8_lossy(&contents).into_owned(),
+                name if name.ends_with(session::BUNDLE_SUFFIX) => {
+                    // a one-off import, not something to follow edits to
+                    self.platform.unwatch_file(name);
+                    match serde_json::from_slice(&contents) {
+                        Ok(bundle) => self.restore(event_loop, bundle),
+                        Err(err) => self.platform.error_reporter()(Box::new(err)),
+                    }
+                }
+                filter::FILTER_FILE => {
+                    let spec = std::str::from_utf8(&contents)
+                        .map_err(|err| err.to_string())
+                        .and_then(str::parse::<filter::Spec>);
+                    match spec {
+                        Ok(spec) => {
+                            println!("filter\n{spec}");
+                            if let Some(shader) = &spec.shader {
+                                if !self.pipelines.contains_key(shader) {
+                                    self.platform.watch_file(shader);
+                                }
+                            }
+                            self.filter = spec;
+                            self.shader_mask = None;
+                            self.refresh_shader_mask();
+                            self.update_filter();
+                        }
+                        Err(err) => self.platform.error_reporter()(err.into()),
+                    }
+                }
+                name if name.ends_with(".wgsl") => {
                     if let Ok(code) = std::str::from_utf8(&contents) {
-                        self.load_shader(code);
-                        self.window.request_redraw();
+                        self.load_shader(name, code);
+                        if self.filter.shader.as_deref() == Some(name) {
+                            self.refresh_shader_mask();
+                        }
+                        self.request_redraw_all();
                     }
                 }
                 _ => {}
@@ -444,5 +2192,9 @@ async fn run() {
 
 pub fn start() {
     Platform::init();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(code) = headless::main(std::env::args().skip(1)) {
+        std::process::exit(code);
+    }
     Platform::run_future(run());
 }
@@ -0,0 +1,231 @@
+//! Bytes hidden in the low bits of the image, read straight from its pixels like zsteg does.
+//!
+//! What to read is written the way zsteg names it, `b1,rgb,msb,xy` for the lowest bit of red,
+//! green then blue in every pixel, row by row, filling each byte from the top. Also accepted are
+//! `b0x<mask>` for arbitrary bits, `lsb` to fill bytes from the bottom, `yx` to go down columns,
+//! and `step=<n>` and `offset=<n>` to only read every nth pixel starting at some pixel.
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{bits, raw::RawImage};
+
+/// Where the GUI reads its spec from. Edits are picked up like shader edits
+pub const SPEC_FILE: &str = "lsb.txt";
+
+/// File signatures worth pointing out, with the extension to save them as
+pub const MAGIC: [(&[u8], &str); 5] = [
+    (b"\x89PNG\r\n\x1a\n", "png"),
+    (b"PK\x03\x04", "zip"),
+    (b"\xff\xd8\xff", "jpg"),
+    (b"%PDF-", "pdf"),
+    (b"OggS", "ogg"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spec {
+    /// Bits of each channel value to read
+    pub mask: u16,
+    /// Channel indices, in the order their bits come out
+    pub channels: Vec<usize>,
+    /// Fill bytes from the top bit, and read the highest masked bit of each value first
+    pub msb_first: bool,
+    /// Walk down each column before moving right, instead of along each row
+    pub column_major: bool,
+    /// Read every `step`th pixel
+    pub step: usize,
+    /// Pixels to skip before reading
+    pub offset: usize,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            mask: 1,
+            channels: vec![0, 1, 2],
+            msb_first: true,
+            column_major: false,
+            step: 1,
+            offset: 0,
+        }
+    }
+}
+
+impl fmt::Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mask.checked_add(1).filter(|x| x.is_power_of_two()) {
+            Some(x) => write!(f, "b{}", x.trailing_zeros())?,
+            None => write!(f, "b{:#x}", self.mask)?,
+        }
+        let channels = bits::channel_names(&self.channels);
+        let order = if self.msb_first { "msb" } else { "lsb" };
+        let scan = if self.column_major { "yx" } else { "xy" };
+        write!(f, ",{channels},{order},{scan}")?;
+        if self.step != 1 {
+            write!(f, ",step={}", self.step)?;
+        }
+        if self.offset != 0 {
+            write!(f, ",offset={}", self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Spec {
+    type Err = String;
+    /// Anything left out keeps its default
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::default();
+        for part in s.trim().split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let number = |value: &str| {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("{value} isn't a valid number in {part}"))
+            };
+            match part {
+                "msb" => ret.msb_first = true,
+                "lsb" => ret.msb_first = false,
+                "xy" => ret.column_major = false,
+                "yx" => ret.column_major = true,
+                _ if part.starts_with("step=") => ret.step = number(&part[5..])?.max(1),
+                _ if part.starts_with("offset=") => ret.offset = number(&part[7..])?,
+                _ if part.starts_with("b0x") => {
+                    ret.mask = u16::from_str_radix(&part[3..], 16)
+                        .ok()
+                        .filter(|&mask| mask != 0)
+                        .ok_or_else(|| format!("{part} isn't a valid bit mask"))?;
+                }
+                _ if part.starts_with('b')
+                    && part[1..].starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    ret.mask = match number(&part[1..])? {
+                        n @ 1..=16 => ((1u32 << n) - 1) as u16,
+                        _ => return Err(format!("{part} should read 1 to 16 bits")),
+                    };
+                }
+                _ => ret.channels = bits::parse_channels(part)?,
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Read up to `limit` bytes. A partial byte at the end is dropped
+pub fn extract(raw: &RawImage, spec: &Spec, limit: usize) -> Vec<u8> {
+    let (width, height) = (raw.width, raw.height);
+    let order: Box<dyn Iterator<Item = (u32, u32)>> = match spec.column_major {
+        false => Box::new((0..height).flat_map(|y| (0..width).map(move |x| (x, y)))),
+        true => Box::new((0..width).flat_map(|x| (0..height).map(move |y| (x, y)))),
+    };
+    let mut bits: Vec<u32> = (0..raw.depth).filter(|b| spec.mask >> b & 1 == 1).collect();
+    if spec.msb_first {
+        bits.reverse();
+    }
+    let mut bytes = Vec::new();
+    let (mut byte, mut count) = (0u8, 0);
+    for (x, y) in order.skip(spec.offset).step_by(spec.step.max(1)) {
+        let pixel = raw.pixel(x, y);
+        for &channel in &spec.channels {
+            for &b in &bits {
+                let bit = (pixel[channel] >> b & 1) as u8;
+                byte = match spec.msb_first {
+                    true => byte << 1 | bit,
+                    false => byte | bit << count,
+                };
+                count += 1;
+                if count == 8 {
+                    if bytes.len() == limit {
+                        return bytes;
+                    }
+                    bytes.push(byte);
+                    (byte, count) = (0, 0);
+                }
+            }
+        }
+    }
+    bytes
+}
+
+/// Extension of a known file type the bytes start with
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    MAGIC
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map(|(_, extension)| *extension)
+}
+
+/// The first `limit` bytes as hex and ASCII, then as UTF-8, and what file they look like
+pub fn preview(bytes: &[u8], limit: usize) -> String {
+    let mut ret = bits::hexdump(bytes, limit);
+    let text: String = String::from_utf8_lossy(&bytes[..bytes.len().min(limit)])
+        .chars()
+        .map(|c| if c.is_control() { '.' } else { c })
+        .collect();
+    ret += &format!("utf-8: {text}\n");
+    if let Some(extension) = sniff(bytes) {
+        ret += &format!("looks like a {extension} file\n");
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x2, row by row: the low bit of red spells 1011 0010 and green's low two bits count
+    /// through 0-3 out of order
+    fn image() -> RawImage {
+        let red = [1, 0, 1, 1, 0, 0, 1, 0];
+        let green = [0b00, 0b11, 0b01, 0b10, 0b11, 0b00, 0b10, 0b01];
+        RawImage {
+            width: 4,
+            height: 2,
+            depth: 8,
+            values: red
+                .iter()
+                .zip(green)
+                .flat_map(|(&r, g)| [100 + r, 200 + g, 0, 255])
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn spec_round_trips() {
+        for text in [
+            "b1,rgb,msb,xy",
+            "b0x5,bgr,lsb,yx,step=3,offset=10",
+            "b8,a,msb,xy",
+            "b0x80,rgba,lsb,xy,offset=1",
+        ] {
+            let spec: Spec = text.parse().unwrap();
+            assert_eq!(spec.to_string(), text);
+        }
+        let spec: Spec = "b2, gr ,yx,step=0".parse().unwrap();
+        assert_eq!(spec.mask, 0b11);
+        assert_eq!(spec.channels, [1, 0]);
+        assert!(spec.column_major && spec.msb_first);
+        assert_eq!(spec.step, 1);
+        for bad in ["b17", "b0x0", "rgbq", "step=x"] {
+            assert!(bad.parse::<Spec>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn extract_follows_spec() {
+        let raw = image();
+        let read = |spec: &str| extract(&raw, &spec.parse().unwrap(), usize::MAX);
+        assert_eq!(read("b1,r,msb,xy"), [0b1011_0010]);
+        // the first bit read goes in the bottom of the byte
+        assert_eq!(read("b1,r,lsb,xy"), [0b0100_1101]);
+        // down the first column, then the second...
+        assert_eq!(read("b1,r,msb,yx"), [0b1000_1110]);
+        // pixels 1, 3, 5 and 7, two bits of red then two of green from each
+        assert_eq!(
+            read("b2,rg,msb,xy,step=2,offset=1"),
+            [0b0011_0110, 0b0000_0001]
+        );
+        // four bits don't make a byte
+        assert!(read("b1,r,msb,xy,step=2").is_empty());
+        assert_eq!(extract(&raw, &"b2,rg".parse().unwrap(), 1).len(), 1);
+    }
+}
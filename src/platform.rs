@@ -1,7 +1,9 @@
-use std::{error::Error, fmt::Debug, future::Future};
+use std::{error::Error, fmt::Debug, future::Future, sync::Arc};
 
 use winit::window::WindowAttributes;
 
+use crate::raw::RawImage;
+
 #[cfg(not(target_arch = "wasm32"))]
 pub mod native;
 #[cfg(target_arch = "wasm32")]
@@ -12,11 +14,20 @@ pub trait PlatformTrait: Debug {
     fn run_future<F: 'static + Future<Output = ()>>(f: F);
     fn init();
     fn set_window_attrs(attrs: WindowAttributes) -> WindowAttributes;
+    /// Like `set_window_attrs`, for windows opened after the first one
+    fn set_extra_window_attrs(attrs: WindowAttributes) -> WindowAttributes;
     fn new(send_event: crate::winit_proxy::SendEvent) -> Self;
     fn watch_file(&mut self, name: &str);
-    #[allow(dead_code)]
     fn unwatch_file(&mut self, name: &str);
     fn list_files(&mut self) -> Vec<String>;
+    /// Write a file the user asked for, to the working directory or as a browser download
+    fn save_file(name: &str, contents: &[u8]) -> Result<(), Box<dyn Error>>;
+    /// Current date and time for file names, like `2024-08-30_21-05-09`
+    fn timestamp() -> String;
+    /// Run [`crate::steg::scan`] without blocking the UI, the hits come back as `Event::ScanHits`
+    fn scan(&mut self, raw: Arc<RawImage>);
+    /// For futures that need to hand results back to the app
+    fn event_sender(&self) -> crate::winit_proxy::SendEvent;
     fn error_reporter(&mut self) -> impl 'static + Send + Sync + Fn(Box<dyn 'static + Error>);
 }
 
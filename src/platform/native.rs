@@ -1,11 +1,20 @@
-use std::{collections::HashSet, error::Error, future::Future, path::Path, sync::mpsc};
+use std::{
+    collections::HashSet,
+    error::Error,
+    future::Future,
+    path::Path,
+    sync::{mpsc, Arc},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use notify::{RecursiveMode, Watcher};
 use winit::window::WindowAttributes;
 
+use crate::{raw::RawImage, winit_proxy::SendEvent};
+
 // very bad impl for testing and stuff
 #[derive(Debug)]
-pub struct Platform(mpsc::SyncSender<(String, bool)>);
+pub struct Platform(mpsc::SyncSender<(String, bool)>, SendEvent);
 
 impl super::PlatformTrait for Platform {
     fn init() {
@@ -17,6 +26,9 @@ impl super::PlatformTrait for Platform {
     fn set_window_attrs(attrs: WindowAttributes) -> WindowAttributes {
         attrs
     }
+    fn set_extra_window_attrs(attrs: WindowAttributes) -> WindowAttributes {
+        attrs
+    }
     fn list_files(&mut self) -> Vec<String> {
         std::fs::read_dir(".")
             .into_iter()
@@ -27,17 +39,48 @@ impl super::PlatformTrait for Platform {
             .flat_map(|name| name.to_str().map(|s| s.to_owned()))
             .collect()
     }
+    fn save_file(name: &str, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+        Ok(std::fs::write(name, contents)?)
+    }
+    fn timestamp() -> String {
+        // UTC, the standard library doesn't know the time zone
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        // days since 1970-01-01 to a civil date, from
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = (secs / 86400) as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+        format!("{year}-{month:02}-{day:02}_{hour:02}-{minute:02}-{second:02}")
+    }
+    fn event_sender(&self) -> SendEvent {
+        self.1.clone()
+    }
+    fn scan(&mut self, raw: Arc<RawImage>) {
+        let send_event = self.1.clone();
+        std::thread::spawn(move || {
+            send_event.send_event(crate::Event::ScanHits(crate::steg::scan(&raw)));
+        });
+    }
     fn watch_file(&mut self, name: &str) {
         self.0.send((name.to_owned(), true)).unwrap()
     }
     fn unwatch_file(&mut self, name: &str) {
         self.0.send((name.to_owned(), false)).unwrap()
     }
-    fn new(send_event: crate::winit_proxy::SendEvent) -> Self {
+    fn new(send_event: SendEvent) -> Self {
         let (watch_tx, watch_rx) = mpsc::sync_channel(16);
         let (watch_tx2, watch_rx2) = mpsc::sync_channel(16);
         let (done_tx, done_rx) = std::sync::mpsc::sync_channel::<()>(1);
-        let ret = Self(watch_tx2);
+        let ret = Self(watch_tx2, send_event.clone());
         let send_event1 = send_event.clone();
         std::thread::spawn(move || {
             while let Ok((a, b)) = watch_rx2.recv() {
@@ -1,9 +1,14 @@
-use std::{error::Error, future::Future, str::FromStr};
+use std::{error::Error, future::Future, rc::Rc, str::FromStr, sync::Arc};
 
 use js_sys::{wasm_bindgen::JsCast, JsString};
 use wasm_bindgen::prelude::*;
 use winit::{platform::web::WindowAttributesExtWebSys, window::WindowAttributes};
 
+use crate::raw::RawImage;
+
+/// Module worker that runs `scan` below with its own copy of the app, served next to the page
+const SCAN_WORKER: &str = "./scan_worker.js";
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(extends = js_sys::Object, js_name = Platform)]
@@ -17,6 +22,12 @@ extern "C" {
         file: &str,
         cb: &wasm_bindgen::closure::Closure<dyn FnMut(String, js_sys::Uint8Array)>,
     );
+    /// `cb` gets files dropped on the page or picked that nothing watches, like bundles
+    #[wasm_bindgen(method, structural, js_class = "Platform", js_name = onImport)]
+    pub fn on_import(
+        this: &JsPlatform,
+        cb: &wasm_bindgen::closure::Closure<dyn FnMut(String, js_sys::Uint8Array)>,
+    );
     #[wasm_bindgen(method, structural, js_class = "Platform", js_name = unwatchFile)]
     pub fn unwatch_file(this: &JsPlatform, file: &str);
     #[wasm_bindgen(method, structural, js_class = "Platform", js_name = reportError)]
@@ -25,7 +36,22 @@ extern "C" {
 
 #[wasm_bindgen(start)]
 fn start() {
-    crate::start();
+    // workers load this module too, but there's no app to start in them
+    if web_sys::window().is_some() {
+        crate::start();
+    }
+}
+
+/// [`crate::steg::scan`] for the scan worker, the hits are returned as JSON
+#[wasm_bindgen(js_name = scan)]
+pub fn scan_worker(width: u32, height: u32, depth: u32, values: Vec<u16>) -> String {
+    let raw = RawImage {
+        width,
+        height,
+        depth,
+        values,
+    };
+    serde_json::to_string(&crate::steg::scan(&raw)).unwrap()
 }
 
 #[derive(Debug)]
@@ -52,9 +78,91 @@ impl super::PlatformTrait for Platform {
             .with_canvas(Some(canvas))
             .with_inner_size(winit::dpi::LogicalSize::new(640.0, 480.0))
     }
+    fn set_extra_window_attrs(attrs: WindowAttributes) -> WindowAttributes {
+        // the page only has one canvas, so let winit add more to the end of the body
+        attrs
+            .with_append(true)
+            .with_inner_size(winit::dpi::LogicalSize::new(640.0, 480.0))
+    }
     fn list_files(&mut self) -> Vec<String> {
         self.1.list_files()
     }
+    fn save_file(name: &str, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+        let js_error = |err: JsValue| format!("{err:?}");
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(js_error)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+        let anchor = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("a")
+            .map_err(js_error)?
+            .dyn_into::<web_sys::HtmlAnchorElement>()
+            .map_err(|_| "not an anchor element")?;
+        anchor.set_href(&url);
+        anchor.set_download(name);
+        anchor.click();
+        web_sys::Url::revoke_object_url(&url).map_err(js_error)?;
+        Ok(())
+    }
+    fn timestamp() -> String {
+        let now = js_sys::Date::new_0();
+        format!(
+            "{}-{:02}-{:02}_{:02}-{:02}-{:02}",
+            now.get_full_year(),
+            now.get_month() + 1,
+            now.get_date(),
+            now.get_hours(),
+            now.get_minutes(),
+            now.get_seconds(),
+        )
+    }
+    fn event_sender(&self) -> crate::winit_proxy::SendEvent {
+        self.0.clone()
+    }
+    fn scan(&mut self, raw: Arc<RawImage>) {
+        let send_event = self.0.clone();
+        let reporter = Rc::new(self.error_reporter());
+        let result = (|| {
+            let options = web_sys::WorkerOptions::new();
+            options.set_type(web_sys::WorkerType::Module);
+            let worker = web_sys::Worker::new_with_options(SCAN_WORKER, &options)?;
+            let (terminate, report) = (worker.clone(), reporter.clone());
+            let onmessage = Closure::once_into_js(move |event: web_sys::MessageEvent| {
+                terminate.terminate();
+                let json = event.data().as_string().unwrap_or_default();
+                match serde_json::from_str(&json) {
+                    Ok(hits) => send_event.send_event(crate::Event::ScanHits(hits)),
+                    Err(err) => report(format!("bad scan results: {err}").into()),
+                }
+            });
+            worker.set_onmessage(Some(onmessage.unchecked_ref()));
+            // a worker that failed to load or panicked never answers, so say why instead
+            let (terminate, report) = (worker.clone(), reporter.clone());
+            let onerror = Closure::once_into_js(move |event: web_sys::ErrorEvent| {
+                terminate.terminate();
+                report(format!("scan worker failed: {}", event.message()).into());
+            });
+            worker.set_onerror(Some(onerror.unchecked_ref()));
+            let (terminate, report) = (worker.clone(), reporter.clone());
+            let onmessageerror = Closure::once_into_js(move |_: web_sys::MessageEvent| {
+                terminate.terminate();
+                report("couldn't read the scan worker's results".into());
+            });
+            worker.set_onmessageerror(Some(onmessageerror.unchecked_ref()));
+            let message = js_sys::Object::new();
+            js_sys::Reflect::set(&message, &"width".into(), &raw.width.into())?;
+            js_sys::Reflect::set(&message, &"height".into(), &raw.height.into())?;
+            js_sys::Reflect::set(&message, &"depth".into(), &raw.depth.into())?;
+            let values = js_sys::Uint16Array::from(&raw.values[..]);
+            js_sys::Reflect::set(&message, &"values".into(), &values)?;
+            worker.post_message(&message)
+        })();
+        if let Err(err) = result {
+            reporter(format!("couldn't start the scan worker: {err:?}").into());
+        }
+    }
     fn watch_file(&mut self, name: &str) {
         log::info!("watch {name}");
         match name {
@@ -82,12 +190,19 @@ impl super::PlatformTrait for Platform {
     }
     fn new(send_event: crate::winit_proxy::SendEvent) -> Self {
         log::info!("new");
-        Self(
-            send_event,
-            JsPlatform::unchecked_from_js(
-                web_sys::window().unwrap().get("platform").unwrap().into(),
-            ),
-        )
+        let platform = JsPlatform::unchecked_from_js(
+            web_sys::window().unwrap().get("platform").unwrap().into(),
+        );
+        let x = send_event.clone();
+        let import = wasm_bindgen::closure::Closure::<dyn FnMut(String, js_sys::Uint8Array)>::new(
+            move |name: String, contents: js_sys::Uint8Array| {
+                x.send_event(crate::Event::FileContents(name, contents.to_vec()));
+            },
+        );
+        platform.on_import(&import);
+        // imports can come in for as long as the page is open
+        import.forget();
+        Self(send_event, platform)
     }
     fn error_reporter(&mut self) -> impl 'static + Send + Sync + Fn(Box<dyn 'static + Error>) {
         |error| log::error!("{error}")
@@ -0,0 +1,51 @@
+//! The image's exact channel values, kept around for tools that care about individual bits. The
+//! texture shaders see is sRGB and mipmapped, so it can't be used for those
+use image::{DynamicImage, ImageBuffer};
+
+#[derive(Debug, Clone)]
+pub struct RawImage {
+    pub width: u32,
+    pub height: u32,
+    /// Bits per channel, 16 for 16-bit images and 8 for everything else
+    pub depth: u32,
+    /// RGBA, row by row. 8-bit values are widened but not scaled
+    pub values: Vec<u16>,
+}
+
+impl RawImage {
+    pub fn new(img: &DynamicImage) -> Self {
+        let (depth, values) = match img {
+            DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => (16, img.to_rgba16().into_raw()),
+            _ => (8, img.to_rgba8().iter().map(|&x| x as u16).collect()),
+        };
+        Self {
+            width: img.width(),
+            height: img.height(),
+            depth,
+            values,
+        }
+    }
+    /// Back to an image at the same depth, for handing to `load_image`
+    pub fn to_image(&self) -> DynamicImage {
+        match self.depth {
+            16 => DynamicImage::ImageRgba16(
+                ImageBuffer::from_raw(self.width, self.height, self.values.clone()).unwrap(),
+            ),
+            _ => DynamicImage::ImageRgba8(
+                ImageBuffer::from_raw(
+                    self.width,
+                    self.height,
+                    self.values.iter().map(|&x| x as u8).collect(),
+                )
+                .unwrap(),
+            ),
+        }
+    }
+    /// RGBA of the pixel at `(x, y)`
+    pub fn pixel(&self, x: u32, y: u32) -> &[u16] {
+        &self.values[(y as usize * self.width as usize + x as usize) * 4..][..4]
+    }
+}
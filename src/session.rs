@@ -0,0 +1,165 @@
+//! Session bundles: one JSON file with everything needed to pick up where someone else left off.
+//! Shader sources, hand-edited settings files and notes are included, images only by hash since
+//! everyone already has them
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    bitplane::Plane, colors::Isolate, compare, ela, filter, spectrum::Shown, split::WipeShape,
+    stereo::Stereo, view::View,
+};
+
+/// Bumped when old bundles can't be read anymore
+pub const BUNDLE_VERSION: u32 = 1;
+/// What bundle file names end with, so they can be told apart from other files
+pub const BUNDLE_SUFFIX: &str = ".bundle.json";
+/// Edited by hand, whatever it says goes into the next bundle saved
+pub const NOTES_FILE: &str = "notes.txt";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: u32,
+    /// SHA-256 of every loaded image file, by name
+    pub images: BTreeMap<String, String>,
+    /// Source of every loaded shader, by name
+    pub shaders: BTreeMap<String, String>,
+    /// Hand-edited settings files by name, restored as if they had just been edited
+    pub settings: BTreeMap<String, String>,
+    /// Annotations for whoever opens the bundle, from `NOTES_FILE`
+    pub notes: String,
+    pub windows: Vec<WindowState>,
+    pub mipmaps: bool,
+    pub lod_bias: f32,
+    /// Notches painted onto spectrum views, on top of the filter file's shapes
+    pub painted: Vec<filter::Shape>,
+    pub ela: ela::Settings,
+    pub compare_mode: compare::Mode,
+    pub compare_offset: (i32, i32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowState {
+    pub viewports: Vec<ViewportState>,
+    pub focused: usize,
+    /// Name of the clear color
+    pub background: String,
+    pub checkerboard: bool,
+    pub outline: bool,
+    pub split: Option<WipeShape>,
+    pub split_pos: (f32, f32),
+    pub split_radius: f32,
+    pub split_swapped: bool,
+    pub isolate: Option<Isolate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewportState {
+    pub shader: String,
+    pub view: View,
+    pub plane: Option<Plane>,
+    /// What part of which spectrum is shown, and whether it's of the shader output
+    pub spectrum: Option<(Shown, bool)>,
+    pub result: bool,
+    pub ela: ela::Mode,
+    pub compare: bool,
+    pub stereo: Option<Stereo>,
+    pub tiles: bool,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bitplane::Channel, spectrum::Component};
+
+    #[test]
+    fn bundle_round_trips() {
+        let bundle = Bundle {
+            version: BUNDLE_VERSION,
+            images: BTreeMap::from([("nuero.png".to_owned(), sha256_hex(b"png"))]),
+            shaders: BTreeMap::from([("shader.wgsl".to_owned(), "// code".to_owned())]),
+            settings: BTreeMap::from([("bits.txt".to_owned(), "r,lsb,xy".to_owned())]),
+            notes: "the red channel hides something".to_owned(),
+            windows: vec![WindowState {
+                viewports: vec![ViewportState {
+                    shader: "shader.wgsl".to_owned(),
+                    view: View {
+                        pos: (0.25, -0.5),
+                        scale: 4.0,
+                        ..View::default()
+                    },
+                    plane: Some(Plane {
+                        channel: Channel::Blue,
+                        bit: 3,
+                        grid: false,
+                        depth: 8,
+                    }),
+                    spectrum: Some((
+                        Shown {
+                            component: Component::Phase,
+                            channel: Some(Channel::Luma),
+                        },
+                        true,
+                    )),
+                    result: true,
+                    ela: ela::Mode::Overlay,
+                    compare: true,
+                    stereo: Some(Stereo {
+                        period: 80.5,
+                        range: 4.0,
+                    }),
+                    tiles: false,
+                }],
+                focused: 0,
+                background: "black".to_owned(),
+                checkerboard: true,
+                outline: false,
+                split: Some(WipeShape::Loupe),
+                split_pos: (100.0, 50.0),
+                split_radius: 20.0,
+                split_swapped: false,
+                isolate: Some(Isolate {
+                    color: [1, 2, 3, 255],
+                    tolerance: 2,
+                }),
+            }],
+            mipmaps: true,
+            lod_bias: -0.5,
+            painted: vec![filter::Shape::Notch {
+                fx: 12.0,
+                fy: -3.0,
+                r: 3.0,
+            }],
+            ela: ela::Settings {
+                quality: 75,
+                scale: 10.0,
+            },
+            compare_mode: compare::Mode::Xor,
+            compare_offset: (-2, 5),
+        };
+        let json = serde_json::to_string(&bundle).unwrap();
+        let back: Bundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        let state = &back.windows[0].viewports[0];
+        assert_eq!(state.view.scale, 4.0);
+        assert_eq!(state.plane, bundle.windows[0].viewports[0].plane);
+        assert_eq!(state.spectrum, bundle.windows[0].viewports[0].spectrum);
+        assert_eq!(state.stereo, bundle.windows[0].viewports[0].stereo);
+        assert_eq!(back.windows[0].isolate, bundle.windows[0].isolate);
+        assert_eq!(back.painted, bundle.painted);
+        assert_eq!(back.ela, bundle.ela);
+        assert_eq!(back.compare_mode, bundle.compare_mode);
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+// drawn under the user shader: a checkerboard behind the image and a dashed outline around it
+
+const OUTLINE_WIDTH: f32 = 2.0;
+const DASH_LENGTH: f32 = 6.0;
+
+@fragment
+fn fs_checkerboard(inp: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(checker_color(inp.pos.xy), 1.0);
+}
+
+// the image quad grown by OUTLINE_WIDTH window pixels on each side
+@vertex
+fn vs_outline(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let pos = vec2<f32>(f32((in_vertex_index & 2u) >> 1), f32(in_vertex_index & 1u));
+    let margin = OUTLINE_WIDTH / (data.scale * view_fit()) / data.img_dim;
+    let corner = pos * (1 + 2 * margin) - margin;
+    var out: VertexOutput;
+    out.tex_coords = vec2<f32>(corner.x, 1 - corner.y);
+    out.pos = vec4<f32>(view_transform(corner), 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_outline(inp: VertexOutput) -> @location(0) vec4<f32> {
+    let t = inp.tex_coords;
+    if (t.x >= 0.0 && t.x <= 1.0 && t.y >= 0.0 && t.y <= 1.0) {
+        discard;
+    }
+    let dash = floor((inp.pos.x + inp.pos.y) / DASH_LENGTH);
+    let shade = f32(i32(dash) % 2 == 0);
+    return vec4<f32>(shade, shade, shade, 1.0);
+}
@@ -0,0 +1,43 @@
+// bit-plane viewer, drawn instead of the user shader. Reads the image's integer values, so no
+// sRGB conversion or filtering can flip a bit
+
+struct Plane {
+    // 0-3 for R, G, B, A, 4 for luma
+    channel: u32,
+    bit: u32,
+    // 1 to show every plane in a grid, a row per channel and the top bit on the left
+    grid: u32,
+    // bits per channel, 8 or 16
+    depth: u32,
+}
+
+@group(1) @binding(0)
+var raw: texture_2d<u32>;
+@group(1) @binding(1)
+var<uniform> plane: Plane;
+
+fn channel_value(texel: vec4<u32>, channel: u32) -> u32 {
+    if (channel < 4u) {
+        return texel[channel];
+    }
+    // Rec. 601 luma, rounded to the nearest integer
+    return (299u * texel.r + 587u * texel.g + 114u * texel.b + 500u) / 1000u;
+}
+
+@fragment
+fn fs_bitplane(inp: VertexOutput) -> @location(0) vec4<f32> {
+    let dim = vec2<f32>(textureDimensions(raw));
+    var uv = inp.tex_coords;
+    var channel = plane.channel;
+    var bit = plane.bit;
+    if (plane.grid != 0u) {
+        let cells = vec2<f32>(f32(plane.depth), 4.0);
+        let cell = min(floor(uv * cells), cells - 1.0);
+        uv = uv * cells - cell;
+        channel = u32(cell.y);
+        bit = plane.depth - 1u - u32(cell.x);
+    }
+    let texel = vec2<u32>(clamp(uv * dim, vec2<f32>(0.0), dim - 1.0));
+    let value = channel_value(textureLoad(raw, texel, 0), channel);
+    return vec4<f32>(vec3<f32>(f32((value >> bit) & 1u)), 1.0);
+}
@@ -0,0 +1,80 @@
+// shared prelude of the built-in shaders, mirrors the boilerplate at the end of shader.wgsl
+// (user shaders only declare the fields up to `rotation`, the rest are for built-in modes)
+struct Data {
+    @location(0) img_dim: vec2<f32>,
+    @location(1) win_dim: vec2<f32>,
+    @location(2) pos: vec2<f32>,
+    @location(3) scale: f32,
+    @location(4) lod_bias: f32,
+    @location(5) flip: vec2<f32>,
+    @location(6) rotation: f32,
+    // 0 off, 1 vertical, 2 horizontal, 3 loupe
+    @location(7) wipe_mode: f32,
+    // divider position / loupe center, in window pixels
+    @location(8) wipe: vec2<f32>,
+    @location(9) wipe_radius: f32,
+    // 1.0 to show the untouched image on the other side
+    @location(10) wipe_swap: f32,
+    @location(11) background: vec4<f32>,
+    @location(12) checkerboard: f32,
+    // repeat of the autostereogram and the shifts searched either side of it, in image pixels
+    @location(13) stereo_period: f32,
+    @location(14) stereo_range: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let pos = vec2<f32>(f32((in_vertex_index & 2u) >> 1), f32(in_vertex_index & 1u));
+    var out: VertexOutput;
+    out.tex_coords = vec2<f32>(pos.x, 1 - pos.y);
+    out.pos = vec4<f32>(view_transform(pos), 0.0, 1.0);
+    return out;
+}
+
+// window pixels per image pixel at scale 1
+fn view_fit() -> f32 {
+    if (data.win_dim.y > data.win_dim.x) {
+        return data.win_dim.x / data.img_dim.x;
+    }
+    return data.win_dim.y / data.img_dim.y;
+}
+
+// image quad corner (0.0-1.0, y up) to clip space: pan, flip, rotate, then zoom
+fn view_transform(corner: vec2<f32>) -> vec2<f32> {
+    let p = ((corner + data.pos) * 2.0 - 1) * data.img_dim / 2 * data.flip;
+    let c = cos(data.rotation);
+    let s = sin(data.rotation);
+    let rotated = vec2<f32>(c * p.x + s * p.y, c * p.y - s * p.x);
+    return rotated * data.scale * view_fit() * 2 / data.win_dim;
+}
+
+@group(0) @binding(0)
+var texture: texture_2d<f32>;
+@group(0) @binding(1)
+var sampler1: sampler;
+@group(0) @binding(2)
+var sampler2: sampler;
+@group(0) @binding(3)
+var<uniform> data: Data;
+// what the frequency filter (D) last produced, a transparent pixel before that
+@group(0) @binding(4)
+var result: texture_2d<f32>;
+// the image with its tiles put back in order (backslash), a transparent pixel before that
+@group(0) @binding(5)
+var tiles: texture_2d<f32>;
+
+const CHECKER_SIZE: f32 = 8.0;
+
+// checkerboard shown behind transparent pixels, in window space
+fn checker_color(frag_pos: vec2<f32>) -> vec3<f32> {
+    let cell = floor(frag_pos / CHECKER_SIZE);
+    if ((i32(cell.x) + i32(cell.y)) % 2 == 0) {
+        return vec3<f32>(0.8, 0.8, 0.8);
+    }
+    return vec3<f32>(0.5, 0.5, 0.5);
+}
@@ -0,0 +1,8 @@
+// error level analysis overlay, with the ELA image bound in place of the image. Unchanged pixels
+// stay see-through so the shader output shows around the differences
+
+@fragment
+fn fs_ela_overlay(inp: VertexOutput) -> @location(0) vec4<f32> {
+    let diff = textureSampleBias(texture, sampler1, inp.tex_coords, data.lod_bias).rgb;
+    return vec4<f32>(diff, max(diff.r, max(diff.g, diff.b)));
+}
@@ -0,0 +1,6 @@
+// frequency filter result viewer, drawn instead of the user shader
+
+@fragment
+fn fs_result(inp: VertexOutput) -> @location(0) vec4<f32> {
+    return clamp(textureSample(result, sampler1, inp.tex_coords), vec4<f32>(0.0), vec4<f32>(1.0));
+}
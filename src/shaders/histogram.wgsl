@@ -0,0 +1,52 @@
+// histogram panel drawn over the bottom of a window, bars already scaled to 0-1 on the CPU
+
+struct Params {
+    // bit per channel, R G B A luma from the bottom
+    channels: u32,
+    // bin under the cursor, or -1
+    cursor_bin: i32,
+}
+
+@group(0) @binding(0)
+var heights: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: Params;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(in_vertex_index & 1u), f32(in_vertex_index >> 1u));
+    var out: VertexOutput;
+    out.pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(inp: VertexOutput) -> @location(0) vec4<f32> {
+    var colors = array<vec3<f32>, 5>(
+        vec3<f32>(1.0, 0.2, 0.2),
+        vec3<f32>(0.2, 1.0, 0.2),
+        vec3<f32>(0.3, 0.4, 1.0),
+        vec3<f32>(0.6, 0.6, 0.6),
+        vec3<f32>(1.0, 1.0, 1.0),
+    );
+    let bin = min(u32(inp.uv.x * 256.0), 255u);
+    let y = 1.0 - inp.uv.y;
+    var color = vec3<f32>(0.0);
+    var alpha = 0.6;
+    for (var c = 0u; c < 5u; c++) {
+        if (((params.channels >> c) & 1u) != 0u && y < textureLoad(heights, vec2<u32>(bin, c), 0).r) {
+            color += colors[c] * 0.6;
+            alpha = 0.9;
+        }
+    }
+    if (i32(bin) == params.cursor_bin) {
+        color += vec3<f32>(0.25);
+    }
+    return vec4<f32>(min(color, vec3<f32>(1.0)), alpha);
+}
@@ -0,0 +1,45 @@
+// counts 256 bins each of R, G, B, A and luma, one invocation per pixel of the region
+
+struct Params {
+    // min inclusive, max exclusive, in image pixels
+    region: vec4<u32>,
+    // how far integer values are shifted down to 8 bits
+    shift: u32,
+}
+
+@group(0) @binding(0)
+var raw: texture_2d<u32>;
+@group(0) @binding(1)
+var output: texture_2d<f32>;
+@group(0) @binding(2)
+var<uniform> params: Params;
+@group(0) @binding(3)
+var<storage, read_write> bins: array<atomic<u32>>;
+
+fn count(value: vec4<u32>) {
+    for (var c = 0u; c < 4u; c++) {
+        atomicAdd(&bins[c * 256u + value[c]], 1u);
+    }
+    // Rec. 601 like the bit-plane viewer
+    let luma = (299u * value.r + 587u * value.g + 114u * value.b + 500u) / 1000u;
+    atomicAdd(&bins[1024u + luma], 1u);
+}
+
+@compute @workgroup_size(16, 16)
+fn count_raw(@builtin(global_invocation_id) id: vec3<u32>) {
+    let pos = params.region.xy + id.xy;
+    if (any(pos >= params.region.zw)) {
+        return;
+    }
+    count(textureLoad(raw, pos, 0) >> vec4<u32>(params.shift));
+}
+
+@compute @workgroup_size(16, 16)
+fn count_output(@builtin(global_invocation_id) id: vec3<u32>) {
+    let pos = params.region.xy + id.xy;
+    if (any(pos >= params.region.zw)) {
+        return;
+    }
+    let value = clamp(textureLoad(output, pos, 0), vec4<f32>(0.0), vec4<f32>(1.0));
+    count(vec4<u32>(round(value * 255.0)));
+}
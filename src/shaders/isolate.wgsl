@@ -0,0 +1,24 @@
+// drawn over the user shader while a color is isolated: pixels within the tolerance of it show
+// through untouched, everything else is dimmed
+
+struct Isolate {
+    color: vec4<u32>,
+    // per channel, in the image's own units
+    tolerance: u32,
+}
+
+@group(1) @binding(0)
+var raw: texture_2d<u32>;
+@group(1) @binding(1)
+var<uniform> isolate: Isolate;
+
+@fragment
+fn fs_isolate(inp: VertexOutput) -> @location(0) vec4<f32> {
+    let dim = vec2<f32>(textureDimensions(raw));
+    let texel = vec2<u32>(clamp(inp.tex_coords * dim, vec2<f32>(0.0), dim - 1.0));
+    let diff = abs(vec4<i32>(textureLoad(raw, texel, 0)) - vec4<i32>(isolate.color));
+    if (all(diff <= vec4<i32>(i32(isolate.tolerance)))) {
+        discard;
+    }
+    return vec4<f32>(0.0, 0.0, 0.0, 0.85);
+}
@@ -0,0 +1,7 @@
+// spectrum viewer, drawn instead of the user shader. The bound texture is the spectrum already
+// centred and scaled on the CPU, so this only has to show it
+
+@fragment
+fn fs_spectrum(inp: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(texture, sampler2, inp.tex_coords).rgb, 1.0);
+}
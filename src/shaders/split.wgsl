@@ -0,0 +1,55 @@
+// drawn over the user shader in split view: the untouched image on one side of the wipe
+
+fn on_raw_side(frag_pos: vec2<f32>) -> bool {
+    var raw = false;
+    if (data.wipe_mode == 1.0) {
+        raw = frag_pos.x < data.wipe.x;
+    } else if (data.wipe_mode == 2.0) {
+        raw = frag_pos.y < data.wipe.y;
+    } else if (data.wipe_mode == 3.0) {
+        raw = distance(frag_pos, data.wipe) < data.wipe_radius;
+    }
+    return raw != (data.wipe_swap != 0.0);
+}
+
+@fragment
+fn fs_raw(inp: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleBias(texture, sampler1, inp.tex_coords, data.lod_bias);
+    if (!on_raw_side(inp.pos.xy)) {
+        discard;
+    }
+    // opaque, so whatever the user shader drew here doesn't show through
+    var under = data.background.rgb;
+    if (data.checkerboard != 0.0) {
+        under = checker_color(inp.pos.xy);
+    }
+    return vec4<f32>(mix(under, color.rgb, color.a), 1.0);
+}
+
+const DIVIDER_WIDTH: f32 = 1.5;
+
+// fullscreen triangle marking the wipe edge
+@vertex
+fn vs_divider(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    var out: VertexOutput;
+    out.tex_coords = uv;
+    out.pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_divider(inp: VertexOutput) -> @location(0) vec4<f32> {
+    var dist = 0.0;
+    if (data.wipe_mode == 1.0) {
+        dist = abs(inp.pos.x - data.wipe.x);
+    } else if (data.wipe_mode == 2.0) {
+        dist = abs(inp.pos.y - data.wipe.y);
+    } else {
+        dist = abs(distance(inp.pos.xy, data.wipe) - data.wipe_radius);
+    }
+    if (dist > DIVIDER_WIDTH) {
+        discard;
+    }
+    return vec4<f32>(1.0, 1.0, 1.0, 0.8);
+}
@@ -0,0 +1,52 @@
+// autostereogram decoder, drawn instead of the user shader. Compares each pixel with the one a
+// period to its left, which it was copied from when the stereogram was made (right near the left
+// edge, where there is nothing to copy from)
+
+// pixels either side of the one being matched, across and down, when searching for the depth
+const MATCH_RADIUS: vec2<i32> = vec2<i32>(2, 1);
+// shifts searched either side of the period, at most. Keep in sync with MAX_RANGE in
+// src/stereo.rs
+const MAX_RANGE: i32 = 32;
+
+fn stereo_sample(pixel: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(texture, sampler1, (pixel + 0.5) / data.img_dim, 0.0).rgb;
+}
+
+// summed channel difference between a pixel and the one `shift` pixels along the row
+fn stereo_difference(pixel: vec2<f32>, shift: f32) -> f32 {
+    var other = pixel.x - shift;
+    if (other < 0.0) {
+        other = pixel.x + shift;
+    }
+    let diff = abs(stereo_sample(pixel) - stereo_sample(vec2<f32>(other, pixel.y)));
+    return diff.r + diff.g + diff.b;
+}
+
+@fragment
+fn fs_stereo(inp: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = floor(inp.tex_coords * data.img_dim);
+    let range = min(i32(data.stereo_range), MAX_RANGE);
+    if (range <= 0) {
+        let diff = stereo_difference(pixel, data.stereo_period) / 3.0;
+        return vec4<f32>(vec3<f32>(diff), 1.0);
+    }
+    // the shift that matches the neighbourhood best, nearer surfaces repeat sooner so come out
+    // brighter
+    var best = 0;
+    var best_cost = 1e9;
+    for (var s = -range; s <= range; s++) {
+        var cost = 0.0;
+        for (var dy = -MATCH_RADIUS.y; dy <= MATCH_RADIUS.y; dy++) {
+            for (var dx = -MATCH_RADIUS.x; dx <= MATCH_RADIUS.x; dx++) {
+                let p = clamp(pixel + vec2<f32>(f32(dx), f32(dy)), vec2<f32>(0.0), data.img_dim - 1.0);
+                cost += stereo_difference(p, data.stereo_period + f32(s));
+            }
+        }
+        if (cost < best_cost) {
+            best_cost = cost;
+            best = s;
+        }
+    }
+    let depth = 1.0 - f32(best + range) / f32(2 * range);
+    return vec4<f32>(vec3<f32>(depth), 1.0);
+}
@@ -0,0 +1,75 @@
+// tile unscrambler, drawn instead of the user shader: the image put back together from `tiles`,
+// the grid, and the index of the tile now at each position in its top left corner
+
+struct TileGrid {
+    // in image pixels
+    tile: vec2<f32>,
+    // position being dragged from, -1 for none
+    selected: f32,
+}
+
+// per position: source index, quarter turns, mirrored
+@group(1) @binding(0)
+var tile_info: texture_2d<u32>;
+@group(1) @binding(1)
+var<uniform> grid: TileGrid;
+
+// 3x5 digits, top row in the high bits
+const DIGITS = array<u32, 10>(0x7b6fu, 0x2c97u, 0x73e7u, 0x73cfu, 0x5bc9u, 0x79cfu, 0x79efu, 0x7249u, 0x7befu, 0x7bcfu);
+// window pixels per font pixel
+const FONT_PIXEL: f32 = 2.0;
+
+fn digit_count(n: u32) -> u32 {
+    var count = 1u;
+    var rest = n / 10u;
+    while (rest > 0u) {
+        count++;
+        rest /= 10u;
+    }
+    return count;
+}
+
+// whether font pixel `p` of the label for `n` is lit, digits are 4 font pixels apart
+fn label_lit(n: u32, p: vec2<u32>) -> bool {
+    let count = digit_count(n);
+    let k = p.x / 4u;
+    let column = p.x % 4u;
+    if (k >= count || column == 3u || p.y >= 5u) {
+        return false;
+    }
+    var digit = n;
+    for (var i = k + 1u; i < count; i++) {
+        digit /= 10u;
+    }
+    var digits = DIGITS;
+    return ((digits[digit % 10u] >> (14u - p.y * 3u - column)) & 1u) == 1u;
+}
+
+@fragment
+fn fs_tiles(inp: VertexOutput) -> @location(0) vec4<f32> {
+    var color = textureSampleBias(tiles, sampler1, inp.tex_coords, data.lod_bias);
+    let pixel = inp.tex_coords * data.img_dim;
+    let cell = floor(pixel / grid.tile);
+    // offset into the tile in window pixels, so the grid and labels stay the same size on screen
+    let local = (pixel - cell * grid.tile) * data.scale * view_fit();
+    if (local.x < 1.0 || local.y < 1.0) {
+        return vec4<f32>(1.0, 0.8, 0.0, 1.0);
+    }
+    let dims = textureDimensions(tile_info);
+    let texel = min(vec2<u32>(cell), dims - 1u);
+    if (f32(texel.y * dims.x + texel.x) == grid.selected) {
+        color = vec4<f32>(mix(color.rgb, vec3<f32>(0.2, 0.5, 1.0), 0.5), 1.0);
+    }
+    let source = textureLoad(tile_info, texel, 0).r;
+    let font = (local - FONT_PIXEL) / FONT_PIXEL;
+    let size = vec2<f32>(f32(digit_count(source) * 4u), 6.0);
+    // leave the label out where the tile is too small on screen to fit it
+    let fits = all((size + 2.0) * FONT_PIXEL < grid.tile * data.scale * view_fit());
+    if (fits && all(font >= vec2<f32>(-1.0)) && all(font < size)) {
+        if (all(font >= vec2<f32>(0.0)) && label_lit(source, vec2<u32>(font))) {
+            return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+        }
+        return vec4<f32>(mix(color.rgb, vec3<f32>(0.0), 0.7), 1.0);
+    }
+    return color;
+}
@@ -0,0 +1,364 @@
+//! Built-in 2D Fourier spectrum viewer. The FFT runs on the CPU once per image (or per render of a
+//! shader's output), then each viewport showing it gets a texture of the part it wants:
+//! log-magnitude or phase, of one channel or of R, G and B at once, with zero frequency centred
+use std::{borrow::Cow, f32::consts::TAU, sync::Arc};
+
+use rustfft::{num_complex::Complex32, FftPlanner};
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroupLayout, Device, Queue, RenderPipeline};
+
+use crate::{bitplane::Channel, raw::RawImage};
+
+/// Filterable, so spectra can be bound in place of the image with the shared layout. Filter
+/// results use it too
+pub const SPECTRUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Component {
+    Magnitude,
+    Phase,
+}
+
+/// What part of a spectrum a viewport shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shown {
+    pub component: Component,
+    /// One channel in gray, or R, G and B in their own colors when `None`
+    pub channel: Option<Channel>,
+}
+
+impl Default for Shown {
+    fn default() -> Self {
+        Self {
+            component: Component::Magnitude,
+            channel: None,
+        }
+    }
+}
+
+impl Shown {
+    pub fn toggle_component(&mut self) {
+        self.component = match self.component {
+            Component::Magnitude => Component::Phase,
+            Component::Phase => Component::Magnitude,
+        };
+    }
+    /// Cycle through RGB, then each channel on its own
+    pub fn step_channel(&mut self, step: i32) {
+        let count = Channel::ALL.len() as i32 + 1;
+        let i = self.channel.map_or(0, |channel| channel.index() as i32 + 1);
+        self.channel = match (i + step).rem_euclid(count) {
+            0 => None,
+            i => Some(Channel::ALL[i as usize - 1]),
+        };
+    }
+    pub fn label(&self) -> String {
+        let component = match self.component {
+            Component::Magnitude => "log-magnitude",
+            Component::Phase => "phase",
+        };
+        let channel = self.channel.map_or("RGB", Channel::name);
+        format!("{component} {channel}")
+    }
+}
+
+/// The 2D DFT of every channel, stored uncentred the way the FFT leaves it
+#[derive(Debug)]
+pub struct Spectrum {
+    pub width: u32,
+    pub height: u32,
+    /// R, G, B, A and luma, each row-major
+    pub channels: Vec<Vec<Complex32>>,
+}
+
+impl Spectrum {
+    /// Transform the image with values scaled to 0-1
+    pub fn new(raw: &RawImage) -> Self {
+        let max = ((1u32 << raw.depth) - 1) as f32;
+        let mut channels: Vec<Vec<Complex32>> = Channel::ALL
+            .iter()
+            .map(|&channel| {
+                raw.values
+                    .chunks_exact(4)
+                    .map(|pixel| {
+                        let value = |c: usize| pixel[c] as f32 / max;
+                        let value = match channel {
+                            // Rec. 601 like the bit-plane viewer
+                            Channel::Luma => 0.299 * value(0) + 0.587 * value(1) + 0.114 * value(2),
+                            channel => value(channel.index()),
+                        };
+                        Complex32::new(value, 0.0)
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut planner = FftPlanner::new();
+        for data in &mut channels {
+            fft_2d(&mut planner, data, raw.width as usize, false);
+        }
+        Self {
+            width: raw.width,
+            height: raw.height,
+            channels,
+        }
+    }
+    /// Value of a channel at a pixel of the centred spectrum
+    pub fn at(&self, channel: Channel, (x, y): (u32, u32)) -> Complex32 {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let i = uncentre(y as usize, height) * width + uncentre(x as usize, width);
+        self.channels[channel.index()][i]
+    }
+    /// Centred RGBA pixels of what `shown` asks for, magnitudes scaled so the largest is 1 and
+    /// phases mapped from -π..π to 0-1. Where a centred filter mask is given, what it takes out
+    /// is darkened
+    pub fn render(&self, shown: Shown, mask: Option<&[f32]>) -> Vec<[f32; 4]> {
+        let channels = match shown.channel {
+            Some(channel) => vec![channel.index()],
+            None => vec![0, 1, 2],
+        };
+        let value = |z: Complex32| match shown.component {
+            Component::Magnitude => z.norm().ln_1p(),
+            Component::Phase => z.arg() / TAU + 0.5,
+        };
+        let scale = match shown.component {
+            Component::Magnitude => {
+                let max = channels
+                    .iter()
+                    .flat_map(|&c| self.channels[c].iter().map(|&z| value(z)))
+                    .fold(0.0, f32::max);
+                if max > 0.0 {
+                    1.0 / max
+                } else {
+                    1.0
+                }
+            }
+            Component::Phase => 1.0,
+        };
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut ret = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = uncentre(y, height) * width;
+            for x in 0..width {
+                let i = row + uncentre(x, width);
+                let v = |c: usize| value(self.channels[c][i]) * scale;
+                let [r, g, b] = match shown.channel {
+                    Some(_) => [v(channels[0]); 3],
+                    None => [v(0), v(1), v(2)],
+                };
+                let shade = mask.map_or(1.0, |mask| 0.25 + 0.75 * mask[y * width + x]);
+                ret.push([r * shade, g * shade, b * shade, 1.0]);
+            }
+        }
+        ret
+    }
+    pub fn texture(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        shown: Shown,
+        mask: Option<&[f32]>,
+    ) -> wgpu::Texture {
+        float_texture(
+            device,
+            queue,
+            "spectrum",
+            (self.width, self.height),
+            &self.render(shown, mask),
+        )
+    }
+}
+
+/// Upload RGBA pixels as a `SPECTRUM_FORMAT` texture
+pub fn float_texture(
+    device: &Device,
+    queue: &Queue,
+    label: &str,
+    (width, height): (u32, u32),
+    pixels: &[[f32; 4]],
+) -> wgpu::Texture {
+    let bytes: Vec<u8> = pixels
+        .iter()
+        .flatten()
+        .flat_map(|&x| half::f16::from_f32(x).to_le_bytes())
+        .collect();
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: SPECTRUM_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        &bytes,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(8 * width),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+    texture
+}
+
+/// In-place 2D FFT of a row-major buffer, rows then columns. Like rustfft, the inverse isn't
+/// normalized
+pub fn fft_2d(planner: &mut FftPlanner<f32>, data: &mut [Complex32], width: usize, inverse: bool) {
+    let height = data.len() / width;
+    let mut plan = |len| match inverse {
+        true => planner.plan_fft_inverse(len),
+        false => planner.plan_fft_forward(len),
+    };
+    plan(width).process(data);
+    let mut transposed = vec![Complex32::default(); data.len()];
+    for (y, row) in data.chunks_exact(width).enumerate() {
+        for (x, &z) in row.iter().enumerate() {
+            transposed[x * height + y] = z;
+        }
+    }
+    plan(height).process(&mut transposed);
+    for (x, column) in transposed.chunks_exact(height).enumerate() {
+        for (y, &z) in column.iter().enumerate() {
+            data[y * width + x] = z;
+        }
+    }
+}
+
+/// Where the FFT put what is shown at `i` of a centred row or column `len` long. Zero frequency
+/// ends up at `len / 2`
+pub fn uncentre(i: usize, len: usize) -> usize {
+    (i + len - len / 2) % len
+}
+
+/// Frequency shown at a pixel of a centred spectrum, in cycles per image
+pub fn frequency((x, y): (f32, f32), (width, height): (f32, f32)) -> (f32, f32) {
+    (
+        x.floor() - (width / 2.0).floor(),
+        y.floor() - (height / 2.0).floor(),
+    )
+}
+
+/// A viewport's spectrum, drawn instead of its shader
+#[derive(Debug)]
+pub struct Layer {
+    pub spectrum: Arc<Spectrum>,
+    pub shown: Shown,
+    /// Of the viewport's shader output rather than of the image
+    pub output: bool,
+    /// Like the viewport's own, but with the spectrum texture bound in place of the image
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Layer {
+    pub fn label(&self) -> String {
+        let of = match self.output {
+            true => "output",
+            false => "image",
+        };
+        format!("spectrum of {of}, {}", self.shown.label())
+    }
+}
+
+#[derive(Debug)]
+pub struct SpectrumViewer {
+    pipeline: RenderPipeline,
+}
+
+impl SpectrumViewer {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("spectrum"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/spectrum.wgsl"),
+            ))),
+        });
+        Self {
+            pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_main",
+                "fs_spectrum",
+                format,
+                None,
+            ),
+        }
+    }
+    /// Draw in place of the user shader, with the layer's bind group at index 0
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass, layer: &Layer) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &layer.bind_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frequency of index `k` of an FFT `len` long, negative in the upper half
+    fn signed(k: usize, len: usize) -> f32 {
+        match k < len.div_ceil(2) {
+            true => k as f32,
+            false => k as f32 - len as f32,
+        }
+    }
+
+    #[test]
+    fn uncentre_puts_zero_in_the_middle() {
+        assert_eq!(
+            (0..5).map(|i| uncentre(i, 5)).collect::<Vec<_>>(),
+            [3, 4, 0, 1, 2]
+        );
+        assert_eq!(
+            (0..4).map(|i| uncentre(i, 4)).collect::<Vec<_>>(),
+            [2, 3, 0, 1]
+        );
+        assert_eq!(uncentre(0, 1), 0);
+        // and agrees with what the title bar says is there
+        for len in [1, 2, 3, 4, 5, 7, 8, 9] {
+            for i in 0..len {
+                let (f, _) = frequency((i as f32 + 0.5, 0.0), (len as f32, 1.0));
+                assert_eq!(f, signed(uncentre(i, len), len), "{i} of {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn odd_sized_cosine_peaks_either_side_of_centre() {
+        // two cycles across a 5x3 image in red
+        let (width, height) = (5, 3);
+        let values = (0..width * height)
+            .flat_map(|i| {
+                let x = (i % width) as f32;
+                let red = 127.5 + 127.5 * (TAU * 2.0 * x / width as f32).cos();
+                [red.round() as u16, 0, 0, 255]
+            })
+            .collect();
+        let spectrum = Spectrum::new(&RawImage {
+            width,
+            height,
+            depth: 8,
+            values,
+        });
+        for y in 0..height {
+            for x in 0..width {
+                let magnitude = spectrum.at(Channel::Red, (x, y)).norm();
+                match (x, y) {
+                    (2, 1) => assert!((magnitude - 7.5).abs() < 0.1, "DC {magnitude}"),
+                    (0 | 4, 1) => assert!((magnitude - 3.75).abs() < 0.1, "{x} {magnitude}"),
+                    _ => assert!(magnitude < 0.1, "({x}, {y}) {magnitude}"),
+                }
+            }
+        }
+    }
+}
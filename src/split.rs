@@ -0,0 +1,95 @@
+//! Before/after split view: the user shader's output on one side of a draggable wipe and the
+//! untouched image on the other, both drawn with the same view transform
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroupLayout, Device, RenderPipeline};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WipeShape {
+    Vertical,
+    Horizontal,
+    Loupe,
+}
+
+impl WipeShape {
+    /// Value of `wipe_mode` in the built-in shaders' `Data`
+    pub fn mode(shape: Option<Self>) -> f32 {
+        match shape {
+            None => 0.0,
+            Some(Self::Vertical) => 1.0,
+            Some(Self::Horizontal) => 2.0,
+            Some(Self::Loupe) => 3.0,
+        }
+    }
+    /// Off -> vertical -> horizontal -> loupe -> off
+    pub fn cycle(shape: Option<Self>) -> Option<Self> {
+        match shape {
+            None => Some(Self::Vertical),
+            Some(Self::Vertical) => Some(Self::Horizontal),
+            Some(Self::Horizontal) => Some(Self::Loupe),
+            Some(Self::Loupe) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Split {
+    raw_pipeline: RenderPipeline,
+    divider_pipeline: RenderPipeline,
+    pub shape: Option<WipeShape>,
+    /// Divider position or loupe center, in window pixels
+    pub pos: (f32, f32),
+    /// Loupe radius, in window pixels
+    pub radius: f32,
+    /// Show the untouched image right of/below the divider, or outside the loupe
+    pub swapped: bool,
+    pub dragging: bool,
+}
+
+impl Split {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("split"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/split.wgsl"),
+            ))),
+        });
+        Self {
+            raw_pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_main",
+                "fs_raw",
+                format,
+                Some(wgpu::BlendState::ALPHA_BLENDING),
+            ),
+            divider_pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_divider",
+                "fs_divider",
+                format,
+                Some(wgpu::BlendState::ALPHA_BLENDING),
+            ),
+            shape: None,
+            pos: (0.0, 0.0),
+            radius: 100.0,
+            swapped: false,
+            dragging: false,
+        }
+    }
+    /// Draw over the user shader's output. Expects the bind group at index 0 to already be set
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        if self.shape.is_none() {
+            return;
+        }
+        rpass.set_pipeline(&self.raw_pipeline);
+        rpass.draw(0..4, 0..1);
+        rpass.set_pipeline(&self.divider_pipeline);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,201 @@
+//! Trying every plausible [`crate::lsb`] spec on an image and ranking what comes out, so nobody
+//! has to click through hundreds of them. Only the first bytes of each are read, which is where
+//! text or a file header would be.
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    lsb::{self, Spec},
+    raw::RawImage,
+};
+
+/// Bytes read per spec
+pub const SCAN_BYTES: usize = 256;
+/// Shorter runs of printable characters turn up by chance all the time
+pub const MIN_TEXT: usize = 8;
+
+const CHANNELS: [&[usize]; 8] = [
+    &[0],
+    &[1],
+    &[2],
+    &[3],
+    &[0, 1, 2],
+    &[2, 1, 0],
+    &[0, 1, 2, 3],
+    &[3, 2, 1, 0],
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Found {
+    /// Starts with a known file signature, by extension
+    File(String),
+    /// Printable ASCII at some byte offset
+    Text { offset: usize, len: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hit {
+    pub spec: Spec,
+    pub found: Found,
+    pub score: usize,
+    /// The text, or the first bytes in hex for files
+    pub preview: String,
+}
+
+impl std::fmt::Display for Hit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let found = match &self.found {
+            Found::File(extension) => format!("{extension} file"),
+            Found::Text { offset, len } => format!("text at {offset}, {len} bytes"),
+        };
+        write!(
+            f,
+            "{:>6}  {:<24} {found}: {}",
+            self.score, self.spec, self.preview
+        )
+    }
+}
+
+/// Every combination of the lowest 1 to 8 bits or a single bit, channel order, bit order and
+/// scan direction
+pub fn specs() -> Vec<Spec> {
+    let masks = (1..=8)
+        .map(|n| (1u16 << n) - 1)
+        .chain((1..8).map(|bit| 1 << bit));
+    let mut ret = Vec::new();
+    for mask in masks {
+        for channels in CHANNELS {
+            for msb_first in [true, false] {
+                for column_major in [false, true] {
+                    ret.push(Spec {
+                        mask,
+                        channels: channels.to_vec(),
+                        msb_first,
+                        column_major,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+    }
+    ret
+}
+
+/// How interesting some bytes look, if at all. File signatures beat any text, and text right at
+/// the start counts double
+pub fn score(bytes: &[u8]) -> Option<(usize, Found)> {
+    if let Some(extension) = lsb::sniff(bytes) {
+        return Some((10 * SCAN_BYTES, Found::File(extension.to_owned())));
+    }
+    let printable = |byte: u8| matches!(byte, 0x20..=0x7e | b'\t' | b'\n' | b'\r');
+    let (mut offset, mut len, mut start) = (0, 0, 0);
+    for i in 0..=bytes.len() {
+        if bytes.get(i).is_some_and(|&byte| printable(byte)) {
+            continue;
+        }
+        if i - start > len {
+            (offset, len) = (start, i - start);
+        }
+        start = i + 1;
+    }
+    // a flat area of the image reads as the same few characters over and over, and a gradient
+    // as letters counting up or down
+    let run = &bytes[offset..][..len];
+    let distinct = run.iter().collect::<HashSet<_>>().len();
+    let smooth = run.windows(2).filter(|w| w[0].abs_diff(w[1]) <= 1).count();
+    if len < MIN_TEXT || distinct < 5 || 2 * smooth > len {
+        return None;
+    }
+    let score = if offset == 0 { 2 * len } else { len };
+    Some((score, Found::Text { offset, len }))
+}
+
+/// Best first
+pub fn scan(raw: &RawImage) -> Vec<Hit> {
+    let mut hits: Vec<Hit> = specs()
+        .into_iter()
+        .filter_map(|spec| {
+            let bytes = lsb::extract(raw, &spec, SCAN_BYTES);
+            let (score, found) = score(&bytes)?;
+            let preview = match &found {
+                Found::File(_) => bytes[..16.min(bytes.len())]
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                Found::Text { offset, len } => bytes[*offset..][..(*len).min(64)]
+                    .iter()
+                    .map(|&byte| match byte {
+                        b'\t' | b'\n' | b'\r' => ' ',
+                        _ => byte as char,
+                    })
+                    .collect(),
+            };
+            Some(Hit {
+                spec,
+                found,
+                score,
+                preview,
+            })
+        })
+        .collect();
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_ranks_files_then_text() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(
+            score(png),
+            Some((10 * SCAN_BYTES, Found::File("png".to_owned())))
+        );
+        let text = b"flag{lsb_is_fun}\x00\x9c";
+        assert_eq!(score(text), Some((32, Found::Text { offset: 0, len: 16 })));
+        // the same text further in only counts once
+        let text = b"\x01\xfe\x07flag{lsb_is_fun}";
+        assert_eq!(score(text), Some((16, Found::Text { offset: 3, len: 16 })));
+        // too short, too repetitive, counting up
+        assert_eq!(score(b"\x00short\x00"), None);
+        assert_eq!(score(b"abababababababab"), None);
+        assert_eq!(score(b"abcdefghijklmnop"), None);
+    }
+
+    #[test]
+    fn scan_finds_text_in_green() {
+        let message = b"the key is under the mat";
+        let bits: Vec<u16> = message
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i & 1) as u16))
+            .collect();
+        // noise everywhere, with the message in the low bit of green from the top left
+        let mut state = 12345u32;
+        let mut noise = || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u16 & 0xff
+        };
+        let values = (0..16 * 16)
+            .flat_map(|i| {
+                let green = match bits.get(i) {
+                    Some(&bit) => noise() & !1 | bit,
+                    None => noise(),
+                };
+                [noise(), green, noise(), 255]
+            })
+            .collect();
+        let raw = RawImage {
+            width: 16,
+            height: 16,
+            depth: 8,
+            values,
+        };
+        let best = &scan(&raw)[0];
+        assert_eq!(best.spec.to_string(), "b1,g,msb,xy");
+        assert!(best.preview.starts_with("the key is under the mat"));
+    }
+}
@@ -0,0 +1,170 @@
+//! Decoding autostereograms (random-dot stereograms). The pattern repeats every `period` pixels
+//! across each row, except where the hidden shape shortens the repeat. Subtracting the image from
+//! itself shifted by the period cancels the background and leaves the shape's outline, and
+//! searching a few shifts either side for the best match gives an actual depth map
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+use wgpu::{BindGroupLayout, Device, RenderPipeline};
+
+use crate::raw::RawImage;
+
+/// Shortest repeat looked for, anything closer is the dots matching their own neighbours
+const MIN_PERIOD: usize = 8;
+/// Rows the autocorrelation is summed over, spread evenly down the image
+const SAMPLE_ROWS: usize = 64;
+/// The first lag whose correlation gets this close to the best is taken, so that multiples of
+/// the period don't win
+const PEAK_RATIO: f32 = 0.8;
+/// Most shifts searched either side of the period. Keep in sync with `MAX_RANGE` in
+/// src/shaders/stereo.wgsl
+pub const MAX_RANGE: f32 = 32.0;
+
+/// A viewport's decoding settings, in image pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stereo {
+    pub period: f32,
+    /// How far either side of the period to search for the best match, 0 just shifts and
+    /// subtracts
+    pub range: f32,
+}
+
+impl Stereo {
+    pub fn new(period: f32) -> Self {
+        Self { period, range: 0.0 }
+    }
+    pub fn label(&self) -> String {
+        if self.range == 0.0 {
+            format!("shift and subtract, period {}", self.period)
+        } else {
+            format!("depth map, period {} ±{}", self.period, self.range)
+        }
+    }
+}
+
+/// Repeat period of the rows, the lag where the luma correlates best with itself
+pub fn estimate_period(raw: &RawImage) -> Option<f32> {
+    let width = raw.width as usize;
+    let max_lag = width / 2;
+    if max_lag <= MIN_PERIOD {
+        return None;
+    }
+    let rows = SAMPLE_ROWS.min(raw.height as usize);
+    let mut correlation = vec![0.0; max_lag + 1];
+    for i in 0..rows {
+        let y = (i * raw.height as usize / rows) as u32;
+        let mut luma: Vec<f32> = (0..raw.width)
+            .map(|x| {
+                let pixel = raw.pixel(x, y);
+                0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32
+            })
+            .collect();
+        let mean = luma.iter().sum::<f32>() / width as f32;
+        luma.iter_mut().for_each(|v| *v -= mean);
+        let variance = luma.iter().map(|v| v * v).sum::<f32>() / width as f32;
+        if variance == 0.0 {
+            continue;
+        }
+        for (lag, sum) in correlation.iter_mut().enumerate().skip(MIN_PERIOD) {
+            let overlap = &luma[..width - lag];
+            let products: f32 = overlap.iter().zip(&luma[lag..]).map(|(a, b)| a * b).sum();
+            *sum += products / overlap.len() as f32 / variance;
+        }
+    }
+    let best = correlation[MIN_PERIOD..]
+        .iter()
+        .copied()
+        .fold(f32::MIN, f32::max);
+    if best <= 0.0 {
+        return None;
+    }
+    // walk up to the top of the first peak that's nearly as high as the best
+    let mut lag = (MIN_PERIOD..=max_lag).find(|&lag| correlation[lag] >= best * PEAK_RATIO)?;
+    while lag < max_lag && correlation[lag + 1] > correlation[lag] {
+        lag += 1;
+    }
+    Some(lag as f32)
+}
+
+/// Shows the decoded stereogram in a viewport, drawn instead of the user shader
+#[derive(Debug)]
+pub struct StereoViewer {
+    pipeline: RenderPipeline,
+}
+
+impl StereoViewer {
+    pub fn new(device: &Device, layout: &BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("stereo"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/stereo.wgsl"),
+            ))),
+        });
+        Self {
+            pipeline: crate::create_quad_pipeline(
+                device,
+                layout,
+                &shader,
+                "vs_main",
+                "fs_stereo",
+                format,
+                Some(wgpu::BlendState::ALPHA_BLENDING),
+            ),
+        }
+    }
+    /// Expects the viewport's bind group at index 0 to already be set
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Random dots repeating every `period` pixels along each row, a new pattern per row
+    fn dots(width: u32, height: u32, period: u32) -> RawImage {
+        let mut seed = 12345u32;
+        let mut values = Vec::new();
+        for _ in 0..height {
+            let pattern: Vec<u16> = (0..period)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                    (seed >> 24) as u16
+                })
+                .collect();
+            for x in 0..width {
+                let value = pattern[(x % period) as usize];
+                values.extend([value, value, value, 255]);
+            }
+        }
+        RawImage {
+            width,
+            height,
+            depth: 8,
+            values,
+        }
+    }
+
+    #[test]
+    fn period_is_found_not_a_multiple() {
+        for period in [13, 37, 64, 90] {
+            assert_eq!(
+                estimate_period(&dots(400, 32, period)),
+                Some(period as f32),
+                "{period}"
+            );
+        }
+        // nothing repeats in a flat image, and there's no room in a narrow one
+        assert_eq!(estimate_period(&dots(400, 32, 1)), None);
+        assert_eq!(estimate_period(&dots(12, 32, 5)), None);
+    }
+
+    #[test]
+    fn max_range_matches_shader() {
+        let line = format!("const MAX_RANGE: i32 = {};", MAX_RANGE as i32);
+        assert!(include_str!("shaders/stereo.wgsl").contains(&line));
+    }
+}
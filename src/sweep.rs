@@ -0,0 +1,292 @@
+//! Render one image with a shader parameter stepped across a range:
+//!
+//! ```text
+//! graphics-toolbox sweep --image clue.png --shader xor.wgsl --param key --from 0 --to 255 \
+//!     --steps 256 -o sweep.gif
+//! ```
+//!
+//! The parameter is a pipeline-overridable constant, declared in the shader like
+//! `override key: f32 = 0.0;`. Sweeping "time" is nothing special, the shader just needs an
+//! `override time: f32` to animate with. Integer overrides only take whole values, so pick
+//! `--steps` to land on them.
+//!
+//! Output depends on `-o`: `.gif` and `.apng` are animations, `--sheet` makes one PNG with every
+//! step in a grid labelled with its value, and anything else is a template for numbered frames
+//! (`{index}`, `{value}`), or a folder to put them in.
+use std::{collections::HashMap, error::Error, fs::File, io::BufWriter, path::Path};
+
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    batch::write_output,
+    export::{self, EXPORT_FORMAT},
+    headless::{number, value, Headless},
+};
+
+#[derive(Debug)]
+pub struct SweepArgs {
+    image: String,
+    shader: String,
+    output: String,
+    param: String,
+    from: f64,
+    to: f64,
+    steps: u32,
+    sheet: bool,
+    columns: Option<u32>,
+    /// Between animation frames, in milliseconds
+    delay: u32,
+    fallback: bool,
+}
+
+impl Default for SweepArgs {
+    fn default() -> Self {
+        Self {
+            image: String::new(),
+            shader: String::new(),
+            output: String::new(),
+            param: "time".to_owned(),
+            from: 0.0,
+            to: 1.0,
+            steps: 10,
+            sheet: false,
+            columns: None,
+            delay: 100,
+            fallback: false,
+        }
+    }
+}
+
+impl SweepArgs {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut ret = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--image" | "-i" => ret.image = value(&mut args, &arg)?,
+                "--shader" | "-s" => ret.shader = value(&mut args, &arg)?,
+                "--output" | "-o" => ret.output = value(&mut args, &arg)?,
+                "--param" | "-p" => ret.param = value(&mut args, &arg)?,
+                "--from" => ret.from = number(&mut args, &arg)?,
+                "--to" => ret.to = number(&mut args, &arg)?,
+                "--steps" | "-n" => ret.steps = number(&mut args, &arg)?,
+                "--sheet" => ret.sheet = true,
+                "--columns" => ret.columns = Some(number(&mut args, &arg)?),
+                "--delay" => ret.delay = number(&mut args, &arg)?,
+                "--fallback" => ret.fallback = true,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if ret.image.is_empty() || ret.shader.is_empty() || ret.output.is_empty() {
+            return Err("--image, --shader and -o are required".to_owned());
+        }
+        if ret.steps == 0 || ret.columns == Some(0) {
+            return Err("--steps and --columns can't be 0".to_owned());
+        }
+        Ok(ret)
+    }
+    fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..self.steps).map(|i| match self.steps {
+            1 => self.from,
+            steps => self.from + (self.to - self.from) * i as f64 / (steps - 1) as f64,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Frames,
+    Gif,
+    Apng,
+    Sheet,
+}
+
+/// Short decimal for file names and labels, without trailing zeros
+fn label(value: f64) -> String {
+    let ret = format!("{value:.4}");
+    let ret = ret.trim_end_matches('0').trim_end_matches('.');
+    match ret {
+        "-0" => "0".to_owned(),
+        _ => ret.to_owned(),
+    }
+}
+
+fn frame_path(template: &str, index: usize, value: f64) -> String {
+    if !template.contains('{') {
+        return Path::new(template)
+            .join(format!("{index:04}.png"))
+            .to_string_lossy()
+            .into_owned();
+    }
+    template
+        .replace("{index}", &format!("{index:04}"))
+        .replace("{value}", &label(value))
+}
+
+/// wgpu quietly ignores constants the shader doesn't have, so a typo would render the same
+/// frame over and over
+fn declares_override(code: &str, name: &str) -> bool {
+    let tokens: Vec<_> = code
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|token| !token.is_empty())
+        .collect();
+    tokens.windows(2).any(|pair| pair == ["override", name])
+}
+
+fn write_gif(path: &str, frames: Vec<RgbaImage>, delay: u32) -> Result<(), Box<dyn Error>> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(frames.into_iter().map(|frame| {
+        image::Frame::from_parts(frame, 0, 0, image::Delay::from_numer_denom_ms(delay, 1))
+    }))?;
+    Ok(())
+}
+
+fn write_apng(
+    path: &str,
+    (width, height): (u32, u32),
+    frames: Vec<RgbaImage>,
+    delay: u32,
+) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+    encoder.set_frame_delay(delay.min(u16::MAX as u32) as u16, 1000)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(&frame)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// 3x5 pixel characters for the contact sheet labels, one row per byte, leftmost pixel in bit 2
+const GLYPHS: [(char, [u8; 5]); 12] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+];
+
+/// White text on a black box in the top left corner of a cell, cut off at its edges
+fn draw_label(sheet: &mut RgbaImage, (x, y, w, h): (u32, u32, u32, u32), text: &str, px: u32) {
+    let box_w = (text.len() as u32 * 4 + 1) * px;
+    let box_h = 7 * px;
+    for dy in 0..box_h.min(h) {
+        for dx in 0..box_w.min(w) {
+            sheet.put_pixel(x + dx, y + dy, Rgba([0, 0, 0, 255]));
+        }
+    }
+    for (i, c) in text.chars().enumerate() {
+        let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == c) else {
+            continue;
+        };
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..3 {
+                if bits >> (2 - col) & 1 == 0 {
+                    continue;
+                }
+                let gx = (1 + i as u32 * 4 + col) * px;
+                let gy = (1 + row as u32) * px;
+                for dy in gy..(gy + px).min(h) {
+                    for dx in gx..(gx + px).min(w) {
+                        sheet.put_pixel(x + dx, y + dy, Rgba([255, 255, 255, 255]));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn contact_sheet(
+    (width, height): (u32, u32),
+    frames: Vec<(f64, RgbaImage)>,
+    columns: Option<u32>,
+) -> RgbaImage {
+    let count = frames.len() as u32;
+    let columns = columns
+        .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32)
+        .clamp(1, count.max(1));
+    let rows = count.div_ceil(columns);
+    // labels stay readable whether the image is tiny or huge
+    let px = (width / 48).clamp(1, 8);
+    let gap = px;
+    let mut sheet = RgbaImage::from_pixel(
+        columns * (width + gap) + gap,
+        rows * (height + gap) + gap,
+        Rgba([32, 32, 32, 255]),
+    );
+    for (i, (value, frame)) in frames.into_iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let x = gap + column * (width + gap);
+        let y = gap + row * (height + gap);
+        image::imageops::replace(&mut sheet, &frame, x as i64, y as i64);
+        draw_label(&mut sheet, (x, y, width, height), &label(value), px);
+    }
+    sheet
+}
+
+pub fn run(args: SweepArgs) -> Result<(), Box<dyn Error>> {
+    let img = image::open(&args.image)?;
+    let code = std::fs::read_to_string(&args.shader)?;
+    if !declares_override(&code, &args.param) {
+        return Err(format!("{} has no `override {}`", args.shader, args.param).into());
+    }
+    let extension = Path::new(&args.output).extension();
+    let output = match extension.and_then(|ext| ext.to_str()) {
+        _ if args.sheet => Output::Sheet,
+        Some("gif") => Output::Gif,
+        Some("apng") => Output::Apng,
+        _ => Output::Frames,
+    };
+    let headless = pollster::block_on(Headless::new(args.fallback))?;
+    let format = match output {
+        Output::Frames => headless.target_format(&frame_path(&args.output, 0, args.from)),
+        _ => EXPORT_FORMAT,
+    };
+    let size = (img.width(), img.height());
+    let mut frames = Vec::new();
+    for (i, value) in args.values().enumerate() {
+        let constants = HashMap::from([(args.param.clone(), value)]);
+        let pixels = pollster::block_on(async {
+            let pipeline = headless
+                .pipeline(&args.shader, &code, format, &constants)
+                .await?;
+            headless.render(&pipeline, img.clone(), format).await
+        })?;
+        log::info!("{} = {}", args.param, label(value));
+        match output {
+            Output::Frames => {
+                let path = frame_path(&args.output, i, value);
+                write_output(Path::new(&path), format, size, pixels)?;
+            }
+            _ => frames.push((
+                value,
+                RgbaImage::from_raw(size.0, size.1, pixels).ok_or("readback has the wrong size")?,
+            )),
+        }
+    }
+    let images =
+        |frames: Vec<(f64, RgbaImage)>| frames.into_iter().map(|(_, frame)| frame).collect();
+    match output {
+        Output::Frames => {}
+        Output::Gif => write_gif(&args.output, images(frames), args.delay)?,
+        Output::Apng => write_apng(&args.output, size, images(frames), args.delay)?,
+        Output::Sheet => {
+            let sheet = contact_sheet(size, frames, args.columns);
+            let size = sheet.dimensions();
+            std::fs::write(&args.output, export::encode_png(size, sheet.into_raw())?)?;
+        }
+    }
+    println!("rendered {} steps of {}", args.steps, args.param);
+    Ok(())
+}
@@ -0,0 +1,488 @@
+//! Unscrambling images that were cut into a grid of tiles and shuffled. Each position in the grid
+//! says which tile goes there and how it's turned, and the image put back together is bound at
+//! binding 5 so user shaders can sample it
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+use wgpu::{BindGroupLayout, Device, Queue, RenderPipeline};
+
+use crate::raw::RawImage;
+
+/// Edited by hand or saved from the viewer, applied whenever it changes
+pub const TILES_FILE: &str = "tiles.txt";
+/// Size of the `TileGrid` uniform
+const GRID_SIZE: usize = 32;
+
+/// How the image is cut up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    /// Tiles this many pixels across and down, the last row and column may be cut short
+    Tile(u32, u32),
+    /// This many columns and rows, with tiles as small as fit them all
+    Grid(u32, u32),
+}
+
+impl Size {
+    /// Size of a tile and how many columns and rows of them cover a `width`×`height` image
+    pub fn resolve(self, (width, height): (u32, u32)) -> ((u32, u32), (u32, u32)) {
+        let (width, height) = (width.max(1), height.max(1));
+        match self {
+            Self::Tile(tw, th) => {
+                let tile = (tw.clamp(1, width), th.clamp(1, height));
+                (tile, (width.div_ceil(tile.0), height.div_ceil(tile.1)))
+            }
+            Self::Grid(cols, rows) => {
+                let tile = (
+                    width.div_ceil(cols.clamp(1, width)),
+                    height.div_ceil(rows.clamp(1, height)),
+                );
+                (tile, (width.div_ceil(tile.0), height.div_ceil(tile.1)))
+            }
+        }
+    }
+}
+
+impl Display for Size {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tile(tw, th) => write!(f, "tile {tw} {th}"),
+            Self::Grid(cols, rows) => write!(f, "grid {cols} {rows}"),
+        }
+    }
+}
+
+/// What goes at one position: tile `source` counting across then down, mirrored left to right
+/// and then turned clockwise `turns` quarter turns. Tiles that aren't square are stretched to fit
+/// when turned a quarter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tile {
+    pub source: u32,
+    /// 0-3
+    pub turns: u8,
+    pub mirrored: bool,
+}
+
+impl Tile {
+    /// Where in the source tile a point of the placed one comes from, both 0-1 across the tile
+    fn source_coords(self, (mut s, mut t): (f32, f32)) -> (f32, f32) {
+        for _ in 0..self.turns {
+            (s, t) = (t, 1.0 - s);
+        }
+        if self.mirrored {
+            s = 1.0 - s;
+        }
+        (s, t)
+    }
+}
+
+/// `17`, `17r1` for a quarter turn, `17f` mirrored, `17r3f` both
+impl Display for Tile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)?;
+        if self.turns != 0 {
+            write!(f, "r{}", self.turns)?;
+        }
+        if self.mirrored {
+            write!(f, "f")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Tile {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rest, mirrored) = match s.strip_suffix('f') {
+            Some(rest) => (rest, true),
+            None => (s, false),
+        };
+        let (source, turns) = rest.split_once('r').unwrap_or((rest, "0"));
+        let err = |err: std::num::ParseIntError| format!("{s}: {err}");
+        Ok(Self {
+            source: source.parse().map_err(err)?,
+            turns: turns.parse::<u8>().map_err(err)? % 4,
+            mirrored,
+        })
+    }
+}
+
+/// What `TILES_FILE` says: a `tile W H` or `grid COLUMNS ROWS` line, then the tiles in grid
+/// order, one row of the grid per line. No tiles means the image as it is. Lines starting with
+/// `#` are comments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arrangement {
+    pub size: Size,
+    pub tiles: Vec<Tile>,
+    /// Columns of the grid over the image last fitted to, only used to write the tiles out a row
+    /// per line
+    cols: u32,
+}
+
+impl Default for Arrangement {
+    fn default() -> Self {
+        Self {
+            size: Size::Grid(4, 4),
+            tiles: Vec::new(),
+            cols: 4,
+        }
+    }
+}
+
+impl Display for Arrangement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.size)?;
+        for row in self.tiles.chunks(self.cols.max(1) as usize) {
+            write!(
+                f,
+                "\n{}",
+                row.iter()
+                    .map(Tile::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Arrangement {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let header = lines.next().ok_or("no tile or grid size")?;
+        let numbers: Vec<_> = header.split_whitespace().skip(1).collect();
+        let number = |token: &str| {
+            token
+                .parse::<u32>()
+                .map_err(|err| format!("{token}: {err}"))
+        };
+        let size = match (header.split_whitespace().next(), &numbers[..]) {
+            (Some("tile"), [w, h]) => Size::Tile(number(w)?, number(h)?),
+            (Some("grid"), [cols, rows]) => Size::Grid(number(cols)?, number(rows)?),
+            _ => return Err(format!("can't understand {header:?}")),
+        };
+        let tiles = lines
+            .flat_map(str::split_whitespace)
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        let cols = match size {
+            Size::Grid(cols, _) => cols,
+            Size::Tile(..) => 1,
+        };
+        Ok(Self { size, tiles, cols })
+    }
+}
+
+impl Arrangement {
+    /// Check the tiles against an image's grid, filling in the image as it is if there are none
+    pub fn fit(&mut self, dim: (u32, u32)) -> Result<(), String> {
+        let (_, (cols, rows)) = self.size.resolve(dim);
+        let count = cols * rows;
+        self.cols = cols;
+        if self.tiles.is_empty() {
+            self.reset(dim);
+        }
+        if self.tiles.len() != count as usize {
+            return Err(format!(
+                "{} tiles for a {cols}×{rows} grid",
+                self.tiles.len()
+            ));
+        }
+        if let Some(tile) = self.tiles.iter().find(|tile| tile.source >= count) {
+            return Err(format!("no tile {} in a {cols}×{rows} grid", tile.source));
+        }
+        Ok(())
+    }
+    /// Every tile back where it started
+    pub fn reset(&mut self, dim: (u32, u32)) {
+        let (_, (cols, rows)) = self.size.resolve(dim);
+        self.cols = cols;
+        self.tiles = (0..cols * rows)
+            .map(|source| Tile {
+                source,
+                ..Default::default()
+            })
+            .collect();
+    }
+    /// Position of the tile under an image pixel, if it's on the image
+    pub fn position(&self, dim: (u32, u32), (x, y): (f32, f32)) -> Option<usize> {
+        let ((tw, th), (cols, _)) = self.size.resolve(dim);
+        if x < 0.0 || y < 0.0 || x >= dim.0 as f32 || y >= dim.1 as f32 {
+            return None;
+        }
+        Some((y as u32 / th * cols + x as u32 / tw) as usize)
+    }
+    /// The image with each position filled from its tile. Parts of tiles past the edge of the
+    /// image come out transparent
+    pub fn apply(&self, raw: &RawImage) -> RawImage {
+        let dim = (raw.width, raw.height);
+        let ((tw, th), (cols, _)) = self.size.resolve(dim);
+        let mut values = Vec::with_capacity(raw.values.len());
+        for y in 0..raw.height {
+            for x in 0..raw.width {
+                let tile = self.tiles[(y / th * cols + x / tw) as usize];
+                let local = (
+                    ((x % tw) as f32 + 0.5) / tw as f32,
+                    ((y % th) as f32 + 0.5) / th as f32,
+                );
+                let (s, t) = tile.source_coords(local);
+                let sx = tile.source % cols * tw + ((s * tw as f32) as u32).min(tw - 1);
+                let sy = tile.source / cols * th + ((t * th as f32) as u32).min(th - 1);
+                if sx < raw.width && sy < raw.height {
+                    values.extend_from_slice(raw.pixel(sx, sy));
+                } else {
+                    values.extend([0; 4]);
+                }
+            }
+        }
+        RawImage {
+            width: raw.width,
+            height: raw.height,
+            depth: raw.depth,
+            values,
+        }
+    }
+    /// One texel per position for the viewer: source, turns and whether it's mirrored
+    fn info(&self, (cols, rows): (u32, u32)) -> RawImage {
+        RawImage {
+            width: cols,
+            height: rows,
+            depth: 16,
+            values: self
+                .tiles
+                .iter()
+                .flat_map(|tile| {
+                    [
+                        tile.source as u16,
+                        tile.turns as u16,
+                        tile.mirrored as u16,
+                        0,
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Draws the put-together image with the grid over it and each tile's source index in its corner
+#[derive(Debug)]
+pub struct TileViewer {
+    pipeline: RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: Option<wgpu::BindGroup>,
+    /// Copy of what was last uploaded and the image size, for the window title
+    shown: Option<(Arrangement, (u32, u32))>,
+}
+
+impl TileViewer {
+    pub fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        plane_layout: &BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("tiles"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("shaders/common.wgsl"),
+                include_str!("shaders/tiles.wgsl"),
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[layout, plane_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("tiles"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_tiles",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tiles"),
+            mapped_at_creation: false,
+            size: GRID_SIZE as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            pipeline,
+            buffer,
+            bind_group: None,
+            shown: None,
+        }
+    }
+    /// Upload where every tile went, after the arrangement or the image changes. `selected` is
+    /// the position being dragged from
+    pub fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        plane_layout: &BindGroupLayout,
+        arrangement: &Arrangement,
+        dim: (u32, u32),
+        selected: Option<usize>,
+    ) {
+        let (tile, grid) = arrangement.size.resolve(dim);
+        let info = crate::bitplane::raw_texture(device, queue, &arrangement.info(grid));
+        let view = info.create_view(&Default::default());
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: plane_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.buffer.as_entire_binding(),
+                },
+            ],
+            label: None,
+        }));
+        let mut bytes = [0u8; GRID_SIZE];
+        for (dst, src) in bytes.chunks_exact_mut(4).zip([
+            tile.0 as f32,
+            tile.1 as f32,
+            selected.map_or(-1.0, |i| i as f32),
+        ]) {
+            dst.copy_from_slice(&src.to_le_bytes());
+        }
+        queue.write_buffer(&self.buffer, 0, &bytes);
+        self.shown = Some((arrangement.clone(), dim));
+    }
+    /// The grid, and what is at the position under `pixel`
+    pub fn label(&self, pixel: Option<(f32, f32)>) -> String {
+        let Some((arrangement, dim)) = &self.shown else {
+            return "tiles".to_owned();
+        };
+        let ((tw, th), (cols, rows)) = arrangement.size.resolve(*dim);
+        let label = format!("tiles {cols}×{rows} of {tw}×{th}");
+        let position = pixel.and_then(|pixel| arrangement.position(*dim, pixel));
+        match position.and_then(|i| Some((i, arrangement.tiles.get(i)?))) {
+            Some((i, tile)) => format!("{label}, position {i} has tile {tile}"),
+            None => label,
+        }
+    }
+    /// Expects the viewport's bind group at index 0 to already be set
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        let Some(bind_group) = &self.bind_group else {
+            return;
+        };
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(1, bind_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `width`×`height` with each pixel's red channel its index, row by row
+    fn numbered(width: u32, height: u32) -> RawImage {
+        RawImage {
+            width,
+            height,
+            depth: 8,
+            values: (0..(width * height) as u16)
+                .flat_map(|i| [i, 0, 0, 255])
+                .collect(),
+        }
+    }
+
+    fn red(raw: &RawImage) -> Vec<u16> {
+        raw.values.chunks(4).map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn source_coords_undo_turns_and_mirroring() {
+        let tile = |text: &str| text.parse::<Tile>().unwrap();
+        assert_eq!(tile("0").source_coords((0.25, 1.0)), (0.25, 1.0));
+        // turned clockwise, the source's top left ends up in the top right
+        assert_eq!(tile("0r1").source_coords((1.0, 0.0)), (0.0, 0.0));
+        assert_eq!(tile("0r1").source_coords((0.0, 0.0)), (0.0, 1.0));
+        assert_eq!(tile("0r2").source_coords((0.0, 0.0)), (1.0, 1.0));
+        assert_eq!(tile("0r3").source_coords((0.0, 0.0)), (1.0, 0.0));
+        assert_eq!(tile("0f").source_coords((0.0, 0.25)), (1.0, 0.25));
+        // mirrored first, so turning back comes before mirroring back
+        assert_eq!(tile("0r1f").source_coords((1.0, 0.0)), (1.0, 0.0));
+        assert_eq!(tile("0r1f").source_coords((0.0, 0.0)), (1.0, 1.0));
+    }
+
+    #[test]
+    fn position_under_pixel() {
+        let dim = (400, 300);
+        let mut arrangement: Arrangement = "grid 4 3".parse().unwrap();
+        arrangement.fit(dim).unwrap();
+        // 100x100 tiles, numbered along rows
+        assert_eq!(arrangement.position(dim, (0.5, 0.5)), Some(0));
+        assert_eq!(arrangement.position(dim, (225.0, 125.0)), Some(6));
+        assert_eq!(arrangement.position(dim, (107.0, 223.0)), Some(9));
+        assert_eq!(arrangement.position(dim, (399.5, 299.5)), Some(11));
+        // off the image
+        assert_eq!(arrangement.position(dim, (-2.0, 5.0)), None);
+        assert_eq!(arrangement.position(dim, (400.0, 5.0)), None);
+    }
+
+    #[test]
+    fn tiles_round_trip() {
+        for text in ["17", "17r1", "17f", "17r3f"] {
+            assert_eq!(text.parse::<Tile>().unwrap().to_string(), text);
+        }
+        assert_eq!("5r6".parse::<Tile>().unwrap().to_string(), "5r2");
+        assert!("x".parse::<Tile>().is_err());
+        assert!("3rq".parse::<Tile>().is_err());
+        let text = "grid 2 2\n3 2r1\n1f 0";
+        let mut arrangement: Arrangement = text.parse().unwrap();
+        arrangement.fit((4, 4)).unwrap();
+        assert_eq!(arrangement.to_string(), text);
+        let mut short: Arrangement = "grid 2 2\n0 1 2".parse().unwrap();
+        assert!(short.fit((4, 4)).is_err());
+        let mut missing: Arrangement = "grid 2 1\n0 2".parse().unwrap();
+        assert!(missing.fit((4, 4)).is_err());
+    }
+
+    #[test]
+    fn apply_moves_turns_and_mirrors_tiles() {
+        // two 2x2 tiles side by side:
+        // 0 1 | 2 3
+        // 4 5 | 6 7
+        let raw = numbered(4, 2);
+        let apply = |text: &str| red(&text.parse::<Arrangement>().unwrap().apply(&raw));
+        assert_eq!(apply("grid 2 1\n0 1"), [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(apply("grid 2 1\n1 0"), [2, 3, 0, 1, 6, 7, 4, 5]);
+        assert_eq!(apply("grid 2 1\n0r1 1f"), [4, 0, 3, 2, 5, 1, 7, 6]);
+        assert_eq!(apply("grid 2 1\n0r2 1r3"), [5, 4, 3, 7, 1, 0, 2, 6]);
+        // the second tile of a 3 pixel wide image only has one column, the rest is transparent
+        let raw = numbered(3, 2);
+        let arrangement: Arrangement = "tile 2 2\n1 0".parse().unwrap();
+        let out = arrangement.apply(&raw);
+        assert_eq!(red(&out), [2, 0, 0, 5, 0, 3]);
+        assert_eq!(out.pixel(1, 0), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,227 @@
+//! Pan/zoom/rotate/flip state of the viewer. The vertex shader applies the same transform, see
+//! `view_transform` in shader.wgsl and src/shaders/common.wgsl; keep them in sync
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Free rotation snaps to the nearest multiple of 90° when it gets this close (in degrees)
+const ROTATION_SNAP: f32 = 3.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct View {
+    /// Pan offset, in units of the image size, applied before flipping and rotating
+    pub pos: (f32, f32),
+    pub scale: f32,
+    /// Clockwise, in degrees, in `0.0..360.0`
+    pub rotation: f32,
+    /// Mirror along the image's x/y axes
+    pub flip: (bool, bool),
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            pos: (0.0, 0.0),
+            scale: 1.0,
+            rotation: 0.0,
+            flip: (false, false),
+        }
+    }
+}
+
+impl View {
+    /// Rotate clockwise by `degrees`, snapping to right angles when close to one
+    pub fn rotate(&mut self, degrees: f32) {
+        let rotation = (self.rotation + degrees).rem_euclid(360.0);
+        let snapped = (rotation / 90.0).round() * 90.0;
+        self.rotation = if (rotation - snapped).abs() < ROTATION_SNAP {
+            snapped % 360.0
+        } else {
+            rotation
+        };
+    }
+    /// Mirror the view left-to-right as it appears on screen
+    pub fn flip_horizontal(&mut self) {
+        self.flip.0 = !self.flip.0;
+        self.rotation = (360.0 - self.rotation) % 360.0;
+    }
+    /// Mirror the view top-to-bottom as it appears on screen
+    pub fn flip_vertical(&mut self) {
+        self.flip.1 = !self.flip.1;
+        self.rotation = (360.0 - self.rotation) % 360.0;
+    }
+    /// Move the image by a screen-space delta (y up), measured in units of the image size along
+    /// the matching axis of the unrotated image
+    pub fn pan(&mut self, dx: f32, dy: f32, img_dim: (f32, f32)) {
+        if img_dim.0 <= 0.0 || img_dim.1 <= 0.0 {
+            return;
+        }
+        let (x, y) = self.unrotate(dx * img_dim.0, dy * img_dim.1);
+        self.pos.0 += x / img_dim.0;
+        self.pos.1 += y / img_dim.1;
+    }
+    /// Image pixel under a window position (both with the origin in the top left corner). The
+    /// result may lie outside the image
+    pub fn screen_to_image(
+        &self,
+        (x, y): (f32, f32),
+        win_dim: (f32, f32),
+        img_dim: (f32, f32),
+    ) -> (f32, f32) {
+        let k = 2.0 * self.scale * fit(win_dim, img_dim);
+        let (x, y) = self.unrotate(
+            (2.0 * x / win_dim.0 - 1.0) * win_dim.0 / k,
+            (1.0 - 2.0 * y / win_dim.1) * win_dim.1 / k,
+        );
+        let corner = (
+            x / img_dim.0 + 0.5 - self.pos.0,
+            y / img_dim.1 + 0.5 - self.pos.1,
+        );
+        (corner.0 * img_dim.0, (1.0 - corner.1) * img_dim.1)
+    }
+    /// Min inclusive and max exclusive image pixels a window shows, clamped to the image
+    pub fn visible_region(&self, win_dim: (f32, f32), img_dim: (f32, f32)) -> [u32; 4] {
+        let (width, height) = win_dim;
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .map(|corner| self.screen_to_image(corner, win_dim, img_dim));
+        let min = corners
+            .iter()
+            .fold((f32::MAX, f32::MAX), |a, b| (a.0.min(b.0), a.1.min(b.1)));
+        let max = corners
+            .iter()
+            .fold((f32::MIN, f32::MIN), |a, b| (a.0.max(b.0), a.1.max(b.1)));
+        let clamp = |v: f32, dim: f32| v.clamp(0.0, dim) as u32;
+        [
+            clamp(min.0.floor(), img_dim.0),
+            clamp(min.1.floor(), img_dim.1),
+            clamp(max.0.ceil(), img_dim.0),
+            clamp(max.1.ceil(), img_dim.1),
+        ]
+    }
+    pub fn rotation_radians(&self) -> f32 {
+        self.rotation * PI / 180.0
+    }
+    pub fn flip_signs(&self) -> (f32, f32) {
+        (
+            if self.flip.0 { -1.0 } else { 1.0 },
+            if self.flip.1 { -1.0 } else { 1.0 },
+        )
+    }
+    /// Undo rotation and flipping of a screen-space vector (y up)
+    fn unrotate(&self, x: f32, y: f32) -> (f32, f32) {
+        let (s, c) = self.rotation_radians().sin_cos();
+        let (fx, fy) = self.flip_signs();
+        ((c * x - s * y) * fx, (s * x + c * y) * fy)
+    }
+}
+
+/// Window pixels per image pixel at scale 1
+fn fit(win_dim: (f32, f32), img_dim: (f32, f32)) -> f32 {
+    if win_dim.1 > win_dim.0 {
+        win_dim.0 / img_dim.0
+    } else {
+        win_dim.1 / img_dim.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `view_transform` from the shaders, then clip space to window pixels (y down)
+    fn to_screen(
+        view: &View,
+        corner: (f32, f32),
+        win_dim: (f32, f32),
+        img_dim: (f32, f32),
+    ) -> (f32, f32) {
+        let (fx, fy) = view.flip_signs();
+        let p = (
+            ((corner.0 + view.pos.0) * 2.0 - 1.0) * img_dim.0 / 2.0 * fx,
+            ((corner.1 + view.pos.1) * 2.0 - 1.0) * img_dim.1 / 2.0 * fy,
+        );
+        let (s, c) = view.rotation_radians().sin_cos();
+        let rotated = (c * p.0 + s * p.1, c * p.1 - s * p.0);
+        let k = view.scale * fit(win_dim, img_dim) * 2.0;
+        let clip = (rotated.0 * k / win_dim.0, rotated.1 * k / win_dim.1);
+        (
+            (clip.0 + 1.0) / 2.0 * win_dim.0,
+            (1.0 - clip.1) / 2.0 * win_dim.1,
+        )
+    }
+
+    #[test]
+    fn screen_to_image_undoes_view_transform() {
+        let (win_dim, img_dim) = ((800.0, 600.0), (400.0, 300.0));
+        let mut views = vec![View::default()];
+        for (rotation, flip, pos, scale) in [
+            (30.0, (false, false), (0.1, -0.2), 2.5),
+            (90.0, (true, false), (-0.3, 0.05), 0.7),
+            (217.0, (false, true), (0.0, 0.4), 1.3),
+            (270.0, (true, true), (0.25, 0.25), 4.0),
+        ] {
+            views.push(View {
+                pos,
+                scale,
+                rotation,
+                flip,
+            });
+        }
+        for view in &views {
+            for corner in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0), (0.3, 0.8)] {
+                let screen = to_screen(view, corner, win_dim, img_dim);
+                let (x, y) = view.screen_to_image(screen, win_dim, img_dim);
+                let expected = (corner.0 * img_dim.0, (1.0 - corner.1) * img_dim.1);
+                assert!(
+                    (x - expected.0).abs() < 1e-2 && (y - expected.1).abs() < 1e-2,
+                    "{view:?} {corner:?}: got {:?}, expected {expected:?}",
+                    (x, y)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn window_corner_at_default_view() {
+        // the image is fitted to the window, rows counted from the top
+        let pixel = View::default().screen_to_image((0.0, 0.0), (800.0, 600.0), (400.0, 300.0));
+        assert_eq!(pixel, (0.0, 0.0));
+        let pixel = View::default().screen_to_image((800.0, 600.0), (800.0, 600.0), (400.0, 300.0));
+        assert_eq!(pixel, (400.0, 300.0));
+        let pixel = View::default().screen_to_image((400.0, 300.0), (800.0, 600.0), (400.0, 300.0));
+        assert_eq!(pixel, (200.0, 150.0));
+    }
+
+    #[test]
+    fn visible_region_follows_zoom_and_pan() {
+        let (win_dim, img_dim) = ((800.0, 600.0), (400.0, 300.0));
+        assert_eq!(
+            View::default().visible_region(win_dim, img_dim),
+            [0, 0, 400, 300]
+        );
+        // zoomed in twice on the middle, then panned a quarter of the image right
+        let mut view = View {
+            scale: 2.0,
+            ..View::default()
+        };
+        assert_eq!(view.visible_region(win_dim, img_dim), [100, 75, 300, 225]);
+        view.pos.0 = 0.25;
+        assert_eq!(view.visible_region(win_dim, img_dim), [0, 75, 200, 225]);
+        // a quarter turn shows a 150x200 pixel box around the middle, rounded outwards
+        let view = View {
+            scale: 2.0,
+            rotation: 90.0,
+            ..View::default()
+        };
+        let region = view.visible_region(win_dim, img_dim);
+        for (got, expected) in region.into_iter().zip([125, 50, 275, 250]) {
+            assert!(got.abs_diff(expected) <= 1, "{region:?}");
+        }
+        // zoomed out, the whole image is inside the window
+        let view = View {
+            scale: 0.5,
+            ..View::default()
+        };
+        assert_eq!(view.visible_region(win_dim, img_dim), [0, 0, 400, 300]);
+    }
+}
@@ -0,0 +1,215 @@
+//! A rectangle of a window that shows the image through one user shader, with its own view state.
+//! Windows tile their viewports in a grid
+use std::sync::Arc;
+
+use wgpu::{BindGroup, BindGroupLayout, BufferUsages, Device};
+
+use crate::{
+    bitplane::{Plane, PLANE_SIZE},
+    compare,
+    data::DATA_SIZE,
+    ela, spectrum,
+    stereo::Stereo,
+    view::View,
+};
+
+/// Position and size of a viewport, in window pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        x >= self.x as f32
+            && y >= self.y as f32
+            && x < (self.x + self.width) as f32
+            && y < (self.y + self.height) as f32
+    }
+}
+
+/// Lay out `count` viewports in a grid that is as square as possible, filling rows first
+pub fn tile(count: usize, width: u32, height: u32) -> Vec<Rect> {
+    let cols = (count as f32).sqrt().ceil().max(1.0) as u32;
+    let rows = (count as u32).div_ceil(cols).max(1);
+    (0..count as u32)
+        .map(|i| {
+            let (col, row) = (i % cols, i / cols);
+            let x = width * col / cols;
+            let y = height * row / rows;
+            Rect {
+                x,
+                y,
+                width: (width * (col + 1) / cols - x).max(1),
+                height: (height * (row + 1) / rows - y).max(1),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct Viewport {
+    pub view: View,
+    /// File name of the user shader this viewport is drawn with
+    pub shader: String,
+    pub data_buffer: wgpu::Buffer,
+    pub bind_group: Option<BindGroup>,
+    /// Drawn as a bit plane of the image instead of through the shader, when set
+    pub plane: Option<Plane>,
+    pub plane_buffer: wgpu::Buffer,
+    pub plane_group: Option<BindGroup>,
+    /// Drawn as a Fourier spectrum instead of through the shader, when set. Takes precedence over
+    /// `plane`
+    pub spectrum: Option<spectrum::Layer>,
+    /// Drawn from the frequency filter's result instead of through the shader
+    pub result: bool,
+    pub ela: ela::Mode,
+    /// Like `bind_group` but with the ELA image as the texture, once there is one
+    pub ela_group: Option<BindGroup>,
+    /// Shows the comparison with slot B through the shader instead of the image
+    pub compare: bool,
+    /// Set while `compare` is and there is something to compare with
+    pub compare_layer: Option<compare::Layer>,
+    /// Decodes the image as an autostereogram instead of running the shader
+    pub stereo: Option<Stereo>,
+    /// Shows the image with its tiles put back in order, with the grid over it
+    pub tiles: bool,
+}
+
+impl Viewport {
+    pub fn new(device: &Device, view: View, shader: String) -> Self {
+        let data_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            mapped_at_creation: false,
+            size: DATA_SIZE as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let plane_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            mapped_at_creation: false,
+            size: PLANE_SIZE as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        Self {
+            view,
+            shader,
+            data_buffer,
+            bind_group: None,
+            plane: None,
+            plane_buffer,
+            plane_group: None,
+            spectrum: None,
+            result: false,
+            ela: Default::default(),
+            ela_group: None,
+            compare: false,
+            compare_layer: None,
+            stereo: None,
+            tiles: false,
+        }
+    }
+    /// Point the bind group at a (re)loaded image, or drop it if there is none yet
+    pub fn update_bind_group(
+        &mut self,
+        device: &Device,
+        layout: &BindGroupLayout,
+        texture: Option<&wgpu::TextureView>,
+        samplers: &[wgpu::Sampler; 2],
+        result: &wgpu::TextureView,
+        tiles: &wgpu::TextureView,
+    ) {
+        self.bind_group = texture
+            .map(|view| self.create_bind_group(device, layout, view, samplers, result, tiles));
+    }
+    /// Bind group with this viewport's `Data`, showing `texture` as the image
+    pub fn create_bind_group(
+        &self,
+        device: &Device,
+        layout: &BindGroupLayout,
+        texture: &wgpu::TextureView,
+        samplers: &[wgpu::Sampler; 2],
+        result: &wgpu::TextureView,
+        tiles: &wgpu::TextureView,
+    ) -> BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(texture),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&samplers[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&samplers[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(
+                        self.data_buffer.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(result),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(tiles),
+                },
+            ],
+            label: None,
+        })
+    }
+    /// Bind the comparison in place of the image while this viewport compares
+    pub fn update_compare_layer(
+        &mut self,
+        device: &Device,
+        layout: &BindGroupLayout,
+        comparison: Option<(&Arc<compare::Summary>, &wgpu::TextureView)>,
+        samplers: &[wgpu::Sampler; 2],
+        result: &wgpu::TextureView,
+        tiles: &wgpu::TextureView,
+    ) {
+        self.compare_layer =
+            comparison
+                .filter(|_| self.compare)
+                .map(|(summary, view)| compare::Layer {
+                    summary: summary.clone(),
+                    bind_group: self
+                        .create_bind_group(device, layout, view, samplers, result, tiles),
+                });
+    }
+    /// Same for the integer copy of the image that bit planes are read from
+    pub fn update_plane_bind_group(
+        &mut self,
+        device: &Device,
+        plane_layout: &BindGroupLayout,
+        raw: Option<&wgpu::TextureView>,
+    ) {
+        self.plane_group = raw.map(|view| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: plane_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(
+                            self.plane_buffer.as_entire_buffer_binding(),
+                        ),
+                    },
+                ],
+                label: None,
+            })
+        });
+    }
+}
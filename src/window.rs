@@ -0,0 +1,530 @@
+//! One OS window (or canvas on the web) with its surface and the viewports tiled inside it
+use std::{collections::HashMap, sync::Arc};
+
+use wgpu::{Device, Queue, RenderPipeline, Surface, SurfaceConfiguration};
+use winit::window::Window;
+
+use crate::{
+    background::Background,
+    bitplane::BitPlanes,
+    colors::{ColorIsolation, Isolate},
+    compare,
+    data::Data,
+    ela::{self, ElaOverlay},
+    filter::ResultViewer,
+    histogram::{self, Histogram, Region},
+    session::{ViewportState, WindowState},
+    spectrum::{self, SpectrumViewer},
+    split::{Split, WipeShape},
+    stereo::StereoViewer,
+    tiles::TileViewer,
+    view::View,
+    viewport::{self, Rect, Viewport},
+    TITLE,
+};
+
+/// Image pixel under a window position, for a viewport laid out at `rect` showing `view`
+fn pixel_at(
+    view: &View,
+    rect: Rect,
+    cursor: (f32, f32),
+    img_dim: (f32, f32),
+) -> Option<(f32, f32)> {
+    if img_dim.0 <= 0.0 || !rect.contains(cursor) {
+        return None;
+    }
+    Some(view.screen_to_image(
+        (cursor.0 - rect.x as f32, cursor.1 - rect.y as f32),
+        (rect.width as f32, rect.height as f32),
+        img_dim,
+    ))
+}
+
+#[derive(Debug)]
+pub struct AppWindow {
+    pub window: Arc<Window>,
+    surface: Surface<'static>,
+    config: SurfaceConfiguration,
+    pub background: Background,
+    bitplanes: BitPlanes,
+    spectrum_viewer: SpectrumViewer,
+    result_viewer: ResultViewer,
+    ela_overlay: ElaOverlay,
+    stereo_viewer: StereoViewer,
+    pub tile_viewer: TileViewer,
+    /// The wipe is in window pixels, so it cuts across all viewports
+    pub split: Split,
+    pub histogram: Histogram,
+    /// Color whose pixels are highlighted in every viewport, the rest dimmed
+    pub isolate: Option<Isolate>,
+    pub isolation: ColorIsolation,
+    pub viewports: Vec<Viewport>,
+    /// Viewport that keyboard and scroll input goes to, the last one the cursor was over
+    pub focused: usize,
+    pub cursor: Option<(f32, f32)>,
+    /// Right button held over a spectrum, painting notches into the filter mask
+    pub painting: bool,
+    /// Grid position the left button went down on in a tile view, to swap with where it comes up
+    pub tile_drag: Option<usize>,
+}
+
+impl AppWindow {
+    pub fn new(
+        device: &Device,
+        adapter: &wgpu::Adapter,
+        layout: &wgpu::BindGroupLayout,
+        plane_layout: &wgpu::BindGroupLayout,
+        window: Arc<Window>,
+        surface: Surface<'static>,
+        viewport: Viewport,
+    ) -> Self {
+        let size = window.inner_size();
+        let config = surface
+            .get_default_config(adapter, size.width.max(640), size.height.max(480))
+            .unwrap();
+        surface.configure(device, &config);
+        Self {
+            background: Background::new(device, layout, config.format),
+            bitplanes: BitPlanes::new(device, layout, plane_layout, config.format),
+            spectrum_viewer: SpectrumViewer::new(device, layout, config.format),
+            result_viewer: ResultViewer::new(device, layout, config.format),
+            ela_overlay: ElaOverlay::new(device, layout, config.format),
+            stereo_viewer: StereoViewer::new(device, layout, config.format),
+            tile_viewer: TileViewer::new(device, layout, plane_layout, config.format),
+            split: Split::new(device, layout, config.format),
+            histogram: Histogram::new(device, config.format),
+            isolate: None,
+            isolation: ColorIsolation::new(device, layout, plane_layout, config.format),
+            window,
+            surface,
+            config,
+            viewports: vec![viewport],
+            focused: 0,
+            cursor: None,
+            painting: false,
+            tile_drag: None,
+        }
+    }
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.config.width = width.max(1);
+        self.config.height = height.max(1);
+        self.surface.configure(device, &self.config);
+    }
+    pub fn rects(&self) -> Vec<Rect> {
+        viewport::tile(self.viewports.len(), self.config.width, self.config.height)
+    }
+    pub fn focused_mut(&mut self) -> &mut Viewport {
+        &mut self.viewports[self.focused]
+    }
+    /// Track the cursor and focus the viewport under it
+    pub fn move_cursor(&mut self, cursor: Option<(f32, f32)>) {
+        self.cursor = cursor;
+        if let Some(i) =
+            cursor.and_then(|cursor| self.rects().iter().position(|r| r.contains(cursor)))
+        {
+            self.focused = i;
+        }
+    }
+    /// Image pixel under the cursor in the focused viewport, if an image is loaded
+    pub fn cursor_pixel(&self, img_dim: (f32, f32)) -> Option<(f32, f32)> {
+        let rect = *self.rects().get(self.focused)?;
+        pixel_at(
+            &self.viewports[self.focused].view,
+            rect,
+            self.cursor?,
+            img_dim,
+        )
+    }
+    /// Part of the image the focused viewport shows, clamped to the image
+    pub fn visible_region(&self, img_dim: (f32, f32)) -> Region {
+        let rect = self.rects()[self.focused];
+        self.viewports[self.focused]
+            .view
+            .visible_region((rect.width as f32, rect.height as f32), img_dim)
+    }
+    fn histogram_bin(&self) -> Option<usize> {
+        self.histogram
+            .bin_at(self.cursor?, self.config.width, self.config.height)
+    }
+    // the closest thing we have to an inspector
+    pub fn update_title(&self, img_dim: (f32, f32)) {
+        if let Some(bin) = self.histogram_bin() {
+            let readout = self.histogram.readout(bin);
+            return self.window.set_title(&format!("{TITLE} - {readout}"));
+        }
+        if let (Some(start), Some(end)) = (self.histogram.selecting, self.cursor_pixel(img_dim)) {
+            let [x0, y0, x1, y1] = histogram::region_between(start, end, img_dim);
+            return self
+                .window
+                .set_title(&format!("{TITLE} - selecting ({x0}, {y0}) to ({x1}, {y1})"));
+        }
+        let viewport = &self.viewports[self.focused];
+        if let Some(layer) = &viewport.spectrum {
+            let label = layer.label();
+            let title = match self.cursor_pixel(img_dim) {
+                Some(pixel) => {
+                    let (fx, fy) = spectrum::frequency(pixel, img_dim);
+                    let inside =
+                        (0.0..img_dim.0).contains(&pixel.0) && (0.0..img_dim.1).contains(&pixel.1);
+                    // a single channel also gets its value, the sum of the 0-1 pixel values at
+                    // zero frequency
+                    let value = match (layer.shown.channel, inside) {
+                        (Some(channel), true) => {
+                            let z = layer.spectrum.at(channel, (pixel.0 as u32, pixel.1 as u32));
+                            format!(", |F| {:.3} arg {:.3}", z.norm(), z.arg())
+                        }
+                        _ => String::new(),
+                    };
+                    format!(
+                        "{TITLE} - {label} ({fx}, {fy}) cycles/image, ({:.4}, {:.4}) cycles/px{value}",
+                        fx / img_dim.0,
+                        fy / img_dim.1
+                    )
+                }
+                None => format!("{TITLE} - {label}"),
+            };
+            return self.window.set_title(&title);
+        }
+        // the filter result, stereogram and tiles are drawn in place of bit planes too
+        let replaced = viewport.result || viewport.stereo.is_some() || viewport.tiles;
+        let Some(plane) = viewport.plane.filter(|_| !replaced) else {
+            let mut shader = match (viewport.result, viewport.stereo, viewport.ela) {
+                (true, _, _) => "filter result".to_owned(),
+                (false, Some(stereo), _) => stereo.label(),
+                (false, None, _) if viewport.tiles => {
+                    self.tile_viewer.label(self.cursor_pixel(img_dim))
+                }
+                (false, None, ela::Mode::Off) => viewport.shader.clone(),
+                (false, None, mode) => format!("{}, ELA {}", viewport.shader, mode.label()),
+            };
+            if viewport.compare && !replaced {
+                match &viewport.compare_layer {
+                    Some(layer) => shader += &format!(", {}", layer.summary),
+                    None => shader += &format!(", nothing in {}", compare::SLOT_B_FILE),
+                }
+            }
+            let title = match self.cursor_pixel(img_dim) {
+                Some((x, y)) => format!("{TITLE} - {shader} ({}, {})", x.floor(), y.floor()),
+                None => format!("{TITLE} - {shader}"),
+            };
+            return self.window.set_title(&title);
+        };
+        // in the grid, name the plane under the cursor too
+        let title = match self.cursor_pixel(plane.shown_dim(img_dim)) {
+            Some(pixel) => {
+                let (under, (x, y)) = plane.locate(pixel, img_dim);
+                let label = plane.label();
+                match plane.grid {
+                    true => format!(
+                        "{TITLE} - {label}, {} ({}, {})",
+                        under.label(),
+                        x.floor(),
+                        y.floor()
+                    ),
+                    false => format!("{TITLE} - {label} ({}, {})", x.floor(), y.floor()),
+                }
+            }
+            None => format!("{TITLE} - {}", plane.label()),
+        };
+        self.window.set_title(&title);
+    }
+    pub fn state(&self) -> WindowState {
+        WindowState {
+            viewports: self
+                .viewports
+                .iter()
+                .map(|viewport| ViewportState {
+                    shader: viewport.shader.clone(),
+                    view: viewport.view.clone(),
+                    plane: viewport.plane,
+                    spectrum: viewport
+                        .spectrum
+                        .as_ref()
+                        .map(|layer| (layer.shown, layer.output)),
+                    result: viewport.result,
+                    ela: viewport.ela,
+                    compare: viewport.compare,
+                    stereo: viewport.stereo,
+                    tiles: viewport.tiles,
+                })
+                .collect(),
+            focused: self.focused,
+            background: self.background.color_name().to_owned(),
+            checkerboard: self.background.checkerboard,
+            outline: self.background.outline,
+            split: self.split.shape,
+            split_pos: self.split.pos,
+            split_radius: self.split.radius,
+            split_swapped: self.split.swapped,
+            isolate: self.isolate,
+        }
+    }
+    /// Go back to a saved state, with `new_viewport` making any viewports that are missing.
+    /// Spectra are left for the app to show again, since they need the image or shader output
+    pub fn restore(
+        &mut self,
+        state: &WindowState,
+        mut new_viewport: impl FnMut(View, String) -> Viewport,
+    ) {
+        if state.viewports.is_empty() {
+            return;
+        }
+        self.viewports.truncate(state.viewports.len());
+        for (i, saved) in state.viewports.iter().enumerate() {
+            let viewport = match self.viewports.get_mut(i) {
+                Some(viewport) => {
+                    viewport.view = saved.view.clone();
+                    viewport.shader.clone_from(&saved.shader);
+                    viewport
+                }
+                None => {
+                    self.viewports
+                        .push(new_viewport(saved.view.clone(), saved.shader.clone()));
+                    self.viewports.last_mut().unwrap()
+                }
+            };
+            viewport.plane = saved.plane;
+            viewport.spectrum = None;
+            viewport.result = saved.result;
+            viewport.ela = saved.ela;
+            viewport.compare = saved.compare;
+            viewport.stereo = saved.stereo;
+            viewport.tiles = saved.tiles;
+        }
+        self.isolate = state.isolate;
+        self.focused = state.focused.min(self.viewports.len() - 1);
+        self.background.set_color(&state.background);
+        self.background.checkerboard = state.checkerboard;
+        self.background.outline = state.outline;
+        self.split.shape = state.split;
+        self.split.pos = state.split_pos;
+        self.split.radius = state.split_radius;
+        self.split.swapped = state.split_swapped;
+        self.window.request_redraw();
+    }
+    fn data(&self, viewport: &Viewport, rect: Rect, img_dim: (f32, f32), lod_bias: f32) -> Data {
+        let background = self.background.clear_color();
+        Data {
+            img_dim: viewport
+                .plane
+                .map_or(img_dim, |plane| plane.shown_dim(img_dim)),
+            win_dim: (rect.width as f32, rect.height as f32),
+            pos: viewport.view.pos,
+            scale: viewport.view.scale,
+            lod_bias,
+            flip: viewport.view.flip_signs(),
+            rotation: viewport.view.rotation_radians(),
+            wipe_mode: WipeShape::mode(self.split.shape),
+            wipe: self.split.pos,
+            wipe_radius: self.split.radius,
+            wipe_swap: self.split.swapped as u8 as f32,
+            background: [
+                background.r as f32,
+                background.g as f32,
+                background.b as f32,
+                background.a as f32,
+            ],
+            checkerboard: self.background.checkerboard as u8 as f32,
+            stereo_period: viewport.stereo.map_or(0.0, |stereo| stereo.period),
+            stereo_range: viewport.stereo.map_or(0.0, |stereo| stereo.range),
+        }
+    }
+    /// Draw every viewport that has both an image and a compiled shader, or shows a bit plane
+    pub fn render(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        pipelines: &HashMap<String, RenderPipeline>,
+        img_dim: (f32, f32),
+        lod_bias: f32,
+    ) {
+        let frame = self
+            .surface
+            .get_current_texture()
+            .expect("Failed to acquire next swap chain texture");
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(device, queue, pipelines, img_dim, lod_bias, &view);
+        drop(view);
+        frame.present();
+    }
+    /// Draw the same frame as `render` into a new texture that can be read back, since the
+    /// swapchain usually can't be copied from
+    pub fn screenshot(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        pipelines: &HashMap<String, RenderPipeline>,
+        img_dim: (f32, f32),
+        lod_bias: f32,
+    ) -> wgpu::Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("screenshot"),
+            size: wgpu::Extent3d {
+                width: self.config.width,
+                height: self.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(device, queue, pipelines, img_dim, lod_bias, &view);
+        texture
+    }
+    fn draw(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        pipelines: &HashMap<String, RenderPipeline>,
+        img_dim: (f32, f32),
+        lod_bias: f32,
+        view: &wgpu::TextureView,
+    ) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.background.clear_color()),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if let Some(isolate) = self.isolate {
+            self.isolation.write(queue, isolate);
+        }
+        for (viewport, rect) in self.viewports.iter().zip(self.rects()) {
+            let Some(group) = &viewport.bind_group else {
+                continue;
+            };
+            let plane = viewport.plane.zip(viewport.plane_group.as_ref());
+            let pipeline = pipelines.get(&viewport.shader);
+            if viewport.spectrum.is_none()
+                && !viewport.result
+                && viewport.stereo.is_none()
+                && !viewport.tiles
+                && plane.is_none()
+                && pipeline.is_none()
+            {
+                continue;
+            }
+            queue.write_buffer(
+                &viewport.data_buffer,
+                0,
+                &self.data(viewport, rect, img_dim, lod_bias).to_bytes(),
+            );
+            rpass.set_viewport(
+                rect.x as f32,
+                rect.y as f32,
+                rect.width as f32,
+                rect.height as f32,
+                0.0,
+                1.0,
+            );
+            rpass.set_bind_group(0, group, &[]);
+            self.background.draw(&mut rpass);
+            match (&viewport.spectrum, plane, pipeline) {
+                (Some(layer), _, _) => {
+                    self.spectrum_viewer.draw(&mut rpass, layer);
+                    // the wipe shows the untouched image
+                    rpass.set_bind_group(0, group, &[]);
+                }
+                (None, _, _) if viewport.result => self.result_viewer.draw(&mut rpass),
+                (None, _, _) if viewport.stereo.is_some() => self.stereo_viewer.draw(&mut rpass),
+                (None, _, _) if viewport.tiles => self.tile_viewer.draw(&mut rpass),
+                (None, Some((plane, plane_group)), _) => {
+                    queue.write_buffer(&viewport.plane_buffer, 0, &plane.to_bytes());
+                    self.bitplanes.draw(&mut rpass, plane_group);
+                }
+                (None, None, Some(pipeline)) => {
+                    rpass.set_pipeline(pipeline);
+                    // the shader sees the comparison or the ELA image in place of the image
+                    let input = match (&viewport.compare_layer, viewport.ela) {
+                        (Some(layer), _) => Some(&layer.bind_group),
+                        (None, ela::Mode::View) => viewport.ela_group.as_ref(),
+                        _ => None,
+                    };
+                    if let Some(input) = input {
+                        rpass.set_bind_group(0, input, &[]);
+                    }
+                    rpass.draw(0..4, 0..1);
+                    if let (ela::Mode::Overlay, Some(ela_group)) =
+                        (viewport.ela, &viewport.ela_group)
+                    {
+                        self.ela_overlay.draw(&mut rpass, ela_group);
+                    }
+                    rpass.set_bind_group(0, group, &[]);
+                }
+                (None, None, None) => {}
+            }
+            // the grid and spectra aren't laid out like the image, so there is nothing to line the
+            // dimming up with
+            if self.isolate.is_some()
+                && viewport.spectrum.is_none()
+                && !viewport.plane.is_some_and(|plane| plane.grid)
+            {
+                self.isolation.draw(&mut rpass);
+            }
+            self.split.draw(&mut rpass);
+        }
+        if self.histogram.counts.is_some() {
+            let panel = Histogram::panel(self.config.width, self.config.height);
+            rpass.set_viewport(
+                panel.x as f32,
+                panel.y as f32,
+                panel.width as f32,
+                panel.height as f32,
+                0.0,
+                1.0,
+            );
+            self.histogram.draw(queue, &mut rpass, self.histogram_bin());
+        }
+        drop(rpass);
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1600x600 window split into two 800x600 viewports, showing a 400x300 image
+    const WIN_DIM: (u32, u32) = (1600, 600);
+    const IMG_DIM: (f32, f32) = (400.0, 300.0);
+
+    #[test]
+    fn cursor_maps_to_pixel_in_second_viewport() {
+        let rect = viewport::tile(2, WIN_DIM.0, WIN_DIM.1)[1];
+        assert_eq!((rect.x, rect.width), (800, 800));
+        // 2 window pixels per image pixel, so (950, 61) is x 75, y 30 in the image
+        let (x, y) = pixel_at(&View::default(), rect, (950.0, 61.0), IMG_DIM).unwrap();
+        assert_eq!((x as u32, y as u32), (75, 30));
+        // the first viewport isn't this one
+        assert_eq!(
+            pixel_at(&View::default(), rect, (150.0, 61.0), IMG_DIM),
+            None
+        );
+        // zoomed out, the window corner is left of and above the image
+        let view = View {
+            scale: 0.5,
+            ..View::default()
+        };
+        let (x, y) = pixel_at(&view, rect, (800.0, 0.0), IMG_DIM).unwrap();
+        assert!(x < 0.0 && y < 0.0);
+    }
+}
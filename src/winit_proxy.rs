@@ -17,12 +17,12 @@ pub enum WinitProxy {
         window_events: Vec<(WindowId, WindowEvent)>,
         user_events: Vec<Event>,
     },
-    Init(App),
+    Init(Box<App>),
 }
 
 #[derive(Debug)]
 pub enum ProxyEvent {
-    Init(App),
+    Init(Box<App>),
     Event(Event),
 }
 
@@ -52,7 +52,7 @@ impl ApplicationHandler<ProxyEvent> for WinitProxy {
                 let fut = App::new(event_loop, crate::Platform::new(SendEvent(proxy.clone())));
                 Platform::run_future(async move {
                     proxy
-                        .send_event(ProxyEvent::Init(fut.await))
+                        .send_event(ProxyEvent::Init(Box::new(fut.await)))
                         .map_err(|_| "event loop closed")
                         .unwrap();
                 });
@@ -5,17 +5,43 @@ import { basicSetup, EditorView } from 'codemirror';
 class Platform {
   constructor() {
     this.watchers = {};
+    // files dropped on the page or picked, by name
+    this.files = {};
+    this.importer = null;
+    const picker = document.getElementById('import');
+    picker.addEventListener('change', () => this.importFiles(picker.files));
+    document.addEventListener('dragover', e => e.preventDefault());
+    document.addEventListener('drop', e => {
+      e.preventDefault();
+      this.importFiles(e.dataTransfer.files);
+    });
+  }
+  // watched files get the new contents like an edit, anything else (bundles, say) goes to the app
+  async importFiles(files) {
+    for (const file of files) {
+      const contents = new Uint8Array(await file.arrayBuffer());
+      this.files[file.name] = contents;
+      if (this.watchers[file.name]) {
+        this.watchers[file.name](contents);
+      } else if (this.importer) {
+        this.importer(file.name, contents);
+      }
+    }
+  }
+  onImport(callback) {
+    this.importer = callback;
   }
   watchFile(name, callback) {
-    if (name == "shader.wgsl") {
-      this.watchers[name] = x => callback(name, x);
+    this.watchers[name] = x => callback(name, x);
+    if (name in this.files) {
+      callback(name, this.files[name]);
     }
   }
   unwatchFile(name) {
     delete this.watchers[name];
   }
   listFiles() {
-    return ['nuero.png', 'shader.wgsl'];
+    return [...new Set(['nuero.png', 'shader.wgsl', ...Object.keys(this.files)])];
   }
   reportError(errorString) {
     console.error(error);
@@ -13,7 +13,15 @@ export default {
     filename: "[name].js"
   },
   plugins: [
-    new CopyPlugin({ patterns: ['./static/index.html'] }),
+    new CopyPlugin({
+      patterns: [
+        './static/index.html',
+        // the scan worker loads its own copy of the module from next to the page, outside the
+        // bundle, so both go into dist as they are
+        '../scan_worker.js',
+        { from: '../pkg/*.{js,wasm}', to: 'pkg/[name][ext]' },
+      ],
+    }),
 
     new WasmPackPlugin({
       crateDirectory: path.resolve(import.meta.dirname, ".."),